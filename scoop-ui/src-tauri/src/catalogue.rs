@@ -0,0 +1,172 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::State;
+
+use crate::error::AppError;
+use crate::manifest_index::{IndexEntry, ManifestIndex};
+use crate::process;
+
+/// 推荐 bucket 列表,与界面的 bucket 页共用同一个文件
+const RECOMMENDED_BUCKETS: &str = include_str!("../../src/lib/data/recommended-buckets.json");
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct RecommendedBucket {
+    pub name: String,
+    pub url: String,
+}
+
+/// 推荐的 bucket,按文件中的顺序
+pub fn recommended_buckets() -> Vec<RecommendedBucket> {
+    serde_json::from_str(RECOMMENDED_BUCKETS).unwrap_or_default()
+}
+
+/// 推荐 bucket 的离线应用清单,用于在 bucket 还没有添加时也能搜到其中的应用
+///
+/// 清单目录的布局与 scoop 根目录相同:还没有添加的推荐 bucket 被浅克隆到 `buckets` 下,
+/// 再用 [`ManifestIndex`] 建立索引,所以搜索时只在 bucket 的 HEAD 变化后才重新解析。
+/// 清单只在用户调用 [`sync_catalogue`] 时下载,克隆失败(例如离线)时保留上次的清单。
+pub struct Catalogue {
+    root: PathBuf,
+    index: ManifestIndex,
+}
+
+impl Catalogue {
+    /// 使用 `root` 下的清单,索引保存在 `root/index.json`
+    pub fn open(root: PathBuf) -> Self {
+        Self {
+            index: ManifestIndex::open(root.join("index.json")),
+            root,
+        }
+    }
+
+    /// 设置保存清单索引失败时的回调,见 [`ManifestIndex::on_error`]
+    pub fn on_index_error(&self, report: impl Fn(AppError) + Send + Sync + 'static) {
+        self.index.on_error(report);
+    }
+
+    /// 索引不保存到磁盘的清单
+    #[cfg(test)]
+    pub fn in_memory(root: PathBuf) -> Self {
+        Self {
+            index: ManifestIndex::in_memory(),
+            root,
+        }
+    }
+
+    /// 克隆或拉取所有不在 `added` 里的推荐 bucket,然后更新索引,返回清单中的应用数
+    ///
+    /// 需要联网和 git。个别 bucket 失败时其余的照常更新,最后返回 [`AppError::CatalogueSyncFailed`]。
+    pub fn sync(&self, added: &HashSet<String>) -> Result<usize, AppError> {
+        let failed: Vec<String> = recommended_buckets()
+            .iter()
+            .filter(|bucket| !added.contains(&bucket.name))
+            .filter_map(|bucket| self.sync_bucket(bucket).err().map(|e| format!("{}: {}", bucket.name, e)))
+            .collect();
+        let count = self.index.entries(&self.root).len();
+        if !failed.is_empty() {
+            return Err(AppError::CatalogueSyncFailed(failed));
+        }
+        Ok(count)
+    }
+
+    fn sync_bucket(&self, bucket: &RecommendedBucket) -> Result<(), String> {
+        let dir = self.root.join("buckets").join(&bucket.name);
+        if dir.join(".git").is_dir() {
+            git(&dir, &["fetch", "--depth", "1", "--quiet", "origin"])?;
+            return git(&dir, &["reset", "--hard", "--quiet", "FETCH_HEAD"]);
+        }
+
+        // 先克隆到 `buckets` 之外的临时目录再改名,中途失败不会留下被索引的半个 bucket
+        let tmp = self.root.join("tmp").join(&bucket.name);
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(self.root.join("tmp")).map_err(|e| e.to_string())?;
+        git(&self.root, &["clone", "--depth", "1", "--quiet", "--", &bucket.url, &tmp.to_string_lossy()])?;
+        std::fs::create_dir_all(self.root.join("buckets")).map_err(|e| e.to_string())?;
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::rename(&tmp, &dir).map_err(|e| e.to_string())
+    }
+
+    /// 清单中的所有条目,调用方按已添加的 bucket 过滤
+    pub fn entries(&self) -> Arc<Vec<IndexEntry>> {
+        self.index.entries(&self.root)
+    }
+}
+
+/// 下载还没有添加的推荐 bucket,之后搜索也能找到其中的应用,返回清单中的应用数
+///
+/// 需要联网和 git,只在用户主动请求时调用。
+#[tauri::command]
+pub async fn sync_catalogue(
+    catalogue: State<'_, Arc<Catalogue>>,
+    index: State<'_, Arc<ManifestIndex>>,
+) -> Result<usize, AppError> {
+    let root = crate::scoop_root()?;
+    let catalogue = catalogue.inner().clone();
+    let index = index.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let added = index.entries(&root).iter().map(|entry| entry.bucket.clone()).collect();
+        catalogue.sync(&added)
+    })
+    .await
+    .map_err(|e| AppError::Io { path: None, message: e.to_string() })?
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<(), String> {
+    let output = process::command("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_recommended_buckets() {
+        let buckets = recommended_buckets();
+        assert!(buckets.iter().any(|bucket| bucket.name == "extras"));
+        assert!(buckets.iter().any(|bucket| bucket.name == "nerd-fonts" && bucket.url.starts_with("https://")));
+    }
+
+    #[test]
+    fn clones_and_updates_buckets() {
+        let temp = std::env::temp_dir().join(format!("scoop-ui-catalogue-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp);
+        let source = temp.join("source");
+        std::fs::create_dir_all(source.join("bucket")).unwrap();
+        let commit = |name: &str, version: &str| {
+            std::fs::write(source.join("bucket").join(format!("{}.json", name)), format!("{{\"version\": \"{}\"}}", version)).unwrap();
+            git(&source, &["add", "."]).unwrap();
+            git(&source, &["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "--quiet", "-m", name]).unwrap();
+        };
+        git(&source, &["init", "--quiet"]).unwrap();
+        commit("font", "1.0");
+
+        let catalogue = Catalogue::in_memory(temp.join("catalogue"));
+        assert!(catalogue.entries().is_empty());
+        let bucket = RecommendedBucket { name: "fonts".to_string(), url: source.to_string_lossy().to_string() };
+        catalogue.sync_bucket(&bucket).unwrap();
+        let entries = catalogue.entries();
+        assert_eq!((entries[0].name.as_str(), entries[0].bucket.as_str()), ("font", "fonts"));
+
+        commit("other-font", "2.0");
+        catalogue.sync_bucket(&bucket).unwrap();
+        assert_eq!(catalogue.entries().len(), 2);
+        assert!(!temp.join("catalogue").join("tmp").join("fonts").exists());
+
+        let missing = RecommendedBucket { name: "missing".to_string(), url: temp.join("nope").to_string_lossy().to_string() };
+        assert!(catalogue.sync_bucket(&missing).is_err());
+        assert_eq!(catalogue.entries().len(), 2);
+
+        let _ = std::fs::remove_dir_all(&temp);
+    }
+}
@@ -0,0 +1,279 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use crate::scoop_command::InvalidArgument;
+
+/// 错误详情中最多保留的 stderr 行数
+const STDERR_TAIL_LINES: usize = 20;
+
+/// 命令返回给界面的错误
+///
+/// 序列化为 `{ code, message, details }`:`code` 是稳定的标识,界面据此区分错误类型;
+/// `message` 可以直接展示给用户;`details` 中是退出码、stderr 末尾几行、相关路径等附加信息。
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    /// 找不到 scoop:根目录无法确定,或者 PowerShell 中没有 scoop 命令
+    ScoopNotFound(String),
+    /// 还没有添加任何 bucket
+    NoBuckets,
+    ManifestNotFound { app: String, bucket: Option<String> },
+    ManifestInvalid { path: PathBuf, reason: String },
+    /// 请求的版本无法安装:manifest 中没有 `autoupdate`,scoop 无法生成该版本的 manifest
+    VersionUnavailable { app: String, version: String },
+    /// manifest 的 `architecture` 中没有请求的架构,`supported` 为其中已有的架构
+    ArchitectureUnsupported { app: String, arch: String, supported: Vec<String> },
+    BucketExists(String),
+    BucketNotFound(String),
+    InvalidArgument(InvalidArgument),
+    /// 界面中设置的 scoop 根目录不可用
+    InvalidRoot { path: PathBuf, reason: String },
+    /// 全局范围的操作需要管理员权限
+    ElevationRequired,
+    /// scoop(或 PowerShell)以非零状态退出
+    CommandFailed { exit_code: Option<i32>, stderr: String },
+    /// 批量更新中有应用更新失败,每个应用的结果在任务的 `app_results` 中
+    UpdatesFailed(Vec<String>),
+    /// 有推荐 bucket 没能下载到离线清单,每项为 `bucket: 原因`
+    CatalogueSyncFailed(Vec<String>),
+    /// 在排队或执行时被用户取消
+    Cancelled,
+    JobNotFound(u64),
+    /// 任务已经结束,无法取消
+    JobFinished(u64),
+    /// 请求取消后任务没有在限定时间内结束
+    JobStillRunning(u64),
+    Io { path: Option<PathBuf>, message: String },
+}
+
+/// 错误的附加信息,没有的字段不会出现在 JSON 中
+#[derive(Default, serde::Serialize)]
+struct ErrorDetails<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stderr: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+}
+
+impl AppError {
+    /// 稳定的错误码,界面按它判断错误类型,不要修改已有的值
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::ScoopNotFound(_) => "scoop_not_found",
+            AppError::NoBuckets => "no_buckets",
+            AppError::ManifestNotFound { .. } => "manifest_not_found",
+            AppError::ManifestInvalid { .. } => "manifest_invalid",
+            AppError::VersionUnavailable { .. } => "version_unavailable",
+            AppError::ArchitectureUnsupported { .. } => "architecture_unsupported",
+            AppError::BucketExists(_) => "bucket_exists",
+            AppError::BucketNotFound(_) => "bucket_not_found",
+            AppError::InvalidArgument(_) => "invalid_argument",
+            AppError::InvalidRoot { .. } => "invalid_root",
+            AppError::ElevationRequired => "elevation_required",
+            AppError::CommandFailed { .. } => "command_failed",
+            AppError::UpdatesFailed(_) => "updates_failed",
+            AppError::CatalogueSyncFailed(_) => "catalogue_sync_failed",
+            AppError::Cancelled => "cancelled",
+            AppError::JobNotFound(_) => "job_not_found",
+            AppError::JobFinished(_) => "job_finished",
+            AppError::JobStillRunning(_) => "job_still_running",
+            AppError::Io { .. } => "io",
+        }
+    }
+
+    fn details(&self) -> ErrorDetails<'_> {
+        match self {
+            AppError::CommandFailed { exit_code, stderr } => ErrorDetails {
+                exit_code: *exit_code,
+                stderr: Some(stderr),
+                ..Default::default()
+            },
+            AppError::ManifestInvalid { path, .. }
+            | AppError::InvalidRoot { path, .. }
+            | AppError::Io { path: Some(path), .. } => ErrorDetails {
+                path: Some(path.to_string_lossy().to_string()),
+                ..Default::default()
+            },
+            _ => ErrorDetails::default(),
+        }
+    }
+
+    /// 子进程以非零状态退出,保留退出码和 stderr(为空则取 stdout)的最后几行
+    ///
+    /// PowerShell 找不到 scoop 命令时返回 [`AppError::ScoopNotFound`]。
+    pub fn command_failed(output: &Output) -> Self {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let text = if stderr.trim().is_empty() {
+            String::from_utf8_lossy(&output.stdout)
+        } else {
+            stderr
+        };
+        Self::exited(output.status.code(), &text)
+    }
+
+    /// 子进程以非零状态退出,`text` 为它的错误输出,只保留最后几行
+    pub fn exited(exit_code: Option<i32>, text: &str) -> Self {
+        if text.contains("is not recognized as") && text.contains("scoop") {
+            return AppError::ScoopNotFound("The scoop command is not available; is Scoop installed?".to_string());
+        }
+        let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+        AppError::CommandFailed {
+            exit_code,
+            stderr: lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n"),
+        }
+    }
+
+    pub fn io(path: &Path, error: std::io::Error) -> Self {
+        AppError::Io {
+            path: Some(path.to_path_buf()),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::ScoopNotFound(reason) => write!(f, "{}", reason),
+            AppError::NoBuckets => write!(f, "No buckets have been added yet"),
+            AppError::ManifestNotFound { app, bucket: Some(bucket) } => {
+                write!(f, "Manifest not found for {} in bucket {}", app, bucket)
+            }
+            AppError::ManifestNotFound { app, bucket: None } => write!(f, "Manifest not found for {}", app),
+            AppError::ManifestInvalid { path, reason } => {
+                write!(f, "Invalid manifest {}: {}", path.display(), reason)
+            }
+            AppError::VersionUnavailable { app, version } => write!(
+                f,
+                "{} {} cannot be installed: its manifest has no autoupdate section to generate other versions from",
+                app, version
+            ),
+            AppError::ArchitectureUnsupported { app, arch, supported } => write!(
+                f,
+                "{} does not support the {} architecture (available: {})",
+                app, arch, supported.join(", ")
+            ),
+            AppError::BucketExists(name) => write!(f, "The '{}' bucket already exists", name),
+            AppError::BucketNotFound(name) => write!(f, "The '{}' bucket is not added", name),
+            AppError::InvalidArgument(error) => write!(f, "{}", error),
+            AppError::InvalidRoot { path, reason } => write!(f, "{} is not a usable Scoop root: {}", path.display(), reason),
+            AppError::ElevationRequired => write!(
+                f,
+                "Installing, updating or uninstalling global apps requires administrator rights; restart Scoop UI as administrator"
+            ),
+            AppError::CommandFailed { exit_code: Some(code), stderr } if stderr.is_empty() => {
+                write!(f, "scoop exited with code {}", code)
+            }
+            AppError::CommandFailed { exit_code: None, stderr } if stderr.is_empty() => write!(f, "scoop failed"),
+            AppError::CommandFailed { stderr, .. } => write!(f, "{}", stderr),
+            AppError::UpdatesFailed(apps) => write!(f, "Failed to update {}", apps.join(", ")),
+            AppError::CatalogueSyncFailed(buckets) => {
+                write!(f, "Failed to download recommended buckets: {}", buckets.join("; "))
+            }
+            AppError::Cancelled => write!(f, "Operation cancelled"),
+            AppError::JobNotFound(id) => write!(f, "Job {} not found", id),
+            AppError::JobFinished(id) => write!(f, "Job {} has already finished", id),
+            AppError::JobStillRunning(id) => write!(f, "Job {} did not stop in time", id),
+            AppError::Io { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<InvalidArgument> for AppError {
+    fn from(error: InvalidArgument) -> Self {
+        AppError::InvalidArgument(error)
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::Io {
+            path: None,
+            message: error.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn output(code: i32, stdout: &str, stderr: &str) -> Output {
+        use std::os::unix::process::ExitStatusExt;
+        Output {
+            status: std::process::ExitStatus::from_raw(code << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[cfg(windows)]
+    fn output(code: i32, stdout: &str, stderr: &str) -> Output {
+        use std::os::windows::process::ExitStatusExt;
+        Output {
+            status: std::process::ExitStatus::from_raw(code as u32),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn serializes_code_message_and_details() {
+        let error = AppError::ManifestNotFound { app: "vscode".to_string(), bucket: Some("main".to_string()) };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "manifest_not_found",
+                "message": "Manifest not found for vscode in bucket main",
+                "details": {},
+            })
+        );
+
+        let error = AppError::from(InvalidArgument::AppName("a;b".to_string()));
+        assert_eq!(serde_json::to_value(&error).unwrap()["code"], "invalid_argument");
+
+        let error = AppError::InvalidRoot { path: PathBuf::from("D:\\empty"), reason: "no apps or buckets directory".to_string() };
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!((json["code"].as_str(), json["details"]["path"].as_str()), (Some("invalid_root"), Some("D:\\empty")));
+        assert_eq!(AppError::JobNotFound(7).to_string(), "Job 7 not found");
+    }
+
+    #[test]
+    fn keeps_the_stderr_tail_of_failed_commands() {
+        let stderr: Vec<String> = (1..=30).map(|i| format!("line {}", i)).collect();
+        let error = AppError::command_failed(&output(1, "", &stderr.join("\n")));
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["code"], "command_failed");
+        assert_eq!(json["details"]["exit_code"], 1);
+        let tail = json["details"]["stderr"].as_str().unwrap();
+        assert_eq!(tail.lines().count(), STDERR_TAIL_LINES);
+        assert!(tail.starts_with("line 11"));
+
+        // stderr 为空时取 stdout
+        let error = AppError::command_failed(&output(2, "ERROR 'nope' isn't a valid bucket", ""));
+        assert_eq!(error.to_string(), "ERROR 'nope' isn't a valid bucket");
+    }
+
+    #[test]
+    fn detects_missing_scoop() {
+        let stderr = "scoop : The term 'scoop' is not recognized as the name of a cmdlet, function, script file, or operable program.";
+        let error = AppError::command_failed(&output(1, "", stderr));
+        assert_eq!(error.code(), "scoop_not_found");
+    }
+}
@@ -0,0 +1,43 @@
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::jobs::{AppResult, JobKind, JobStatus};
+use crate::scoop_output::ScoopPhase;
+
+/// 所有 scoop 操作共用的事件名
+pub const OPERATION_EVENT: &str = "operation-event";
+
+/// [`OperationEvent`] 的结构版本,字段有不兼容的改动时递增
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// `operation-event` 的负载
+///
+/// 每个任务依次发送 `queued`、`running`(开始时一次,之后每行输出一次)和一个终态
+/// (`completed`/`failed`/`cancelled`)。前端只需订阅一次,通过 `job_id` 关联到具体任务。
+#[derive(Clone, Serialize)]
+pub struct OperationEvent {
+    pub schema_version: u32,
+    pub job_id: u64,
+    pub operation: JobKind,
+    pub targets: Vec<String>,
+    pub status: JobStatus,
+    /// 当前输出行解析出的阶段
+    pub phase: Option<ScoopPhase>,
+    /// 0.0-1.0,由阶段推算,只增不减
+    pub progress: Option<f32>,
+    /// 下载阶段要下载的文件大小。scoop 的输出被重定向时不显示下载进度,所以没有已下载的字节数
+    pub bytes_total: Option<u64>,
+    /// scoop 输出的原始行
+    pub line: Option<String>,
+    /// 终态事件的结果:成功时为任务给出的结果说明(如 "git 安装成功!"),失败时为错误信息
+    pub message: Option<String>,
+    /// 失败时的错误:`{ code, message, details }`,命令失败时 `details` 中有退出码
+    pub error: Option<AppError>,
+    /// 取消后留下的未装完的目录
+    pub leftover_dirs: Vec<String>,
+    /// 批量任务终态事件中每个应用的结果
+    pub app_results: Vec<AppResult>,
+    pub created_at: u64,          // Unix timestamp in milliseconds
+    pub started_at: Option<u64>,
+    pub timestamp: u64,
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::error::AppError;
+use crate::jobs::{AppResult, JobKind, JobManager, JobStatus};
+use crate::paths::Scope;
+
+/// 操作历史中的一条记录,每个修改类任务结束时写入一条
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub job_id: u64,
+    pub operation: JobKind,
+    pub targets: Vec<String>,
+    #[serde(default)]
+    pub scope: Scope,
+    pub status: JobStatus,
+    /// 任务实际执行的命令行
+    pub commands: Vec<String>,
+    pub exit_code: Option<i32>,
+    pub created_at: u64,          // Unix timestamp in milliseconds
+    pub started_at: Option<u64>,
+    pub finished_at: u64,
+    pub message: Option<String>,
+    /// 操作完成后各应用的版本,从 scoop 输出中解析
+    pub versions: BTreeMap<String, String>,
+    pub leftover_dirs: Vec<String>,
+    #[serde(default)]
+    pub app_results: Vec<AppResult>,
+}
+
+/// 持久化的操作历史
+///
+/// 记录保存在应用数据目录下的 `history/history.jsonl`,每行一条 [`HistoryEntry`];
+/// 完整的输出单独保存为 `history/logs/<job_id>.log`,避免查询历史时读入大量输出。
+pub struct History {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl History {
+    pub fn open(dir: PathBuf) -> Self {
+        let _ = std::fs::create_dir_all(dir.join("logs"));
+        Self { dir, lock: Mutex::new(()) }
+    }
+
+    fn entries_path(&self) -> PathBuf {
+        self.dir.join("history.jsonl")
+    }
+
+    fn log_path(&self, job_id: u64) -> PathBuf {
+        self.dir.join("logs").join(format!("{}.log", job_id))
+    }
+
+    /// 追加一条记录及其完整输出
+    pub fn append(&self, entry: &HistoryEntry, output: &[String]) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+
+        let mut log = output.join("\n");
+        log.push('\n');
+        std::fs::write(self.log_path(entry.job_id), log)
+            .map_err(|e| format!("Failed to write operation log: {}", e))?;
+
+        let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.entries_path())
+            .map_err(|e| format!("Failed to open history: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write history: {}", e))
+    }
+
+    /// 读取全部记录,按写入顺序(即结束时间)排列。无法解析的行会被跳过
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        let _guard = self.lock.lock().unwrap();
+        let Ok(file) = std::fs::File::open(self.entries_path()) else {
+            return Vec::new();
+        };
+        BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect()
+    }
+
+    /// 历史中最大的任务 ID,用于重启后继续编号
+    pub fn last_job_id(&self) -> u64 {
+        self.entries().iter().map(|entry| entry.job_id).max().unwrap_or(0)
+    }
+
+    pub fn read_log(&self, job_id: u64) -> Option<String> {
+        std::fs::read_to_string(self.log_path(job_id)).ok()
+    }
+
+    /// 按应用和创建时间过滤记录,最新的在前,最多返回 `limit` 条
+    pub fn query(&self, app: Option<&str>, since: Option<u64>, until: Option<u64>, limit: Option<usize>) -> Vec<HistoryEntry> {
+        let mut entries: Vec<HistoryEntry> = self
+            .entries()
+            .into_iter()
+            .filter(|entry| app.is_none_or(|app| entry.involves(app)))
+            .filter(|entry| since.is_none_or(|since| entry.created_at >= since))
+            .filter(|entry| until.is_none_or(|until| entry.created_at <= until))
+            .collect();
+        entries.reverse();
+        if let Some(limit) = limit {
+            entries.truncate(limit);
+        }
+        entries
+    }
+}
+
+impl HistoryEntry {
+    /// 是否涉及某个应用:目标中包含它,或者操作改变了它的版本(例如批量更新)
+    fn involves(&self, app: &str) -> bool {
+        self.targets.iter().any(|target| target.eq_ignore_ascii_case(app))
+            || self.versions.keys().any(|name| name.eq_ignore_ascii_case(app))
+    }
+}
+
+/// 查询操作历史,最新的在前
+///
+/// `app` 按应用名过滤(不区分大小写);`since`/`until` 为毫秒时间戳,按任务创建时间过滤。
+#[tauri::command]
+pub fn get_operation_history(
+    history: State<'_, Arc<History>>,
+    app: Option<String>,
+    since: Option<u64>,
+    until: Option<u64>,
+    limit: Option<usize>,
+) -> Vec<HistoryEntry> {
+    history.query(app.as_deref(), since, until, limit)
+}
+
+/// 获取任务的完整输出。任务仍在运行时返回目前为止的输出
+#[tauri::command]
+pub fn get_operation_log(
+    history: State<'_, Arc<History>>,
+    jobs: State<'_, JobManager>,
+    job_id: u64,
+) -> Result<String, AppError> {
+    if let Some(output) = jobs.running_output(job_id) {
+        return Ok(output.join("\n"));
+    }
+    history.read_log(job_id).ok_or(AppError::JobNotFound(job_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(job_id: u64, operation: JobKind, targets: &[&str], created_at: u64) -> HistoryEntry {
+        HistoryEntry {
+            job_id,
+            operation,
+            targets: targets.iter().map(|target| target.to_string()).collect(),
+            scope: Scope::User,
+            status: JobStatus::Completed,
+            commands: Vec::new(),
+            exit_code: Some(0),
+            created_at,
+            started_at: Some(created_at),
+            finished_at: created_at + 1,
+            message: None,
+            versions: BTreeMap::new(),
+            leftover_dirs: Vec::new(),
+            app_results: Vec::new(),
+        }
+    }
+
+    fn open(name: &str) -> (History, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("scoop-ui-history-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        (History::open(dir.clone()), dir)
+    }
+
+    #[test]
+    fn appends_entries_and_logs() {
+        let (history, dir) = open("append");
+        assert!(history.entries().is_empty());
+        assert_eq!(history.last_job_id(), 0);
+
+        history.append(&entry(3, JobKind::Install, &["git"], 100), &["Installing git".to_string()]).unwrap();
+        history.append(&entry(2, JobKind::Uninstall, &["7zip"], 200), &[]).unwrap();
+        std::fs::OpenOptions::new().append(true).open(dir.join("history.jsonl")).unwrap()
+            .write_all(b"not json\n").unwrap();
+
+        let entries = history.entries();
+        assert_eq!(entries.iter().map(|entry| entry.job_id).collect::<Vec<_>>(), vec![3, 2]);
+        assert_eq!(history.last_job_id(), 3);
+        assert_eq!(history.read_log(3).as_deref(), Some("Installing git\n"));
+        assert!(history.read_log(4).is_none());
+
+        // 重新打开后记录仍在
+        assert_eq!(History::open(dir.clone()).last_job_id(), 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn filters_by_app_and_time() {
+        let (history, dir) = open("query");
+        let mut update_all = entry(3, JobKind::UpdateAll, &["*"], 300);
+        update_all.versions.insert("Git".to_string(), "2.45.0".to_string());
+        for entry in [entry(1, JobKind::Install, &["git"], 100), entry(2, JobKind::Install, &["main/7zip"], 200), update_all] {
+            history.append(&entry, &[]).unwrap();
+        }
+        let ids = |entries: Vec<HistoryEntry>| entries.iter().map(|entry| entry.job_id).collect::<Vec<_>>();
+
+        assert_eq!(ids(history.query(None, None, None, None)), vec![3, 2, 1]);
+        assert_eq!(ids(history.query(Some("GIT"), None, None, None)), vec![3, 1]);
+        assert_eq!(ids(history.query(None, Some(200), None, None)), vec![3, 2]);
+        assert_eq!(ids(history.query(None, None, Some(200), None)), vec![2, 1]);
+        assert_eq!(ids(history.query(None, Some(150), Some(250), None)), vec![2]);
+        assert_eq!(ids(history.query(None, None, None, Some(2))), vec![3, 2]);
+        assert_eq!(ids(history.query(Some("git"), None, None, Some(1))), vec![3]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn involves_targets_and_changed_versions() {
+        let install = entry(1, JobKind::Install, &["Git"], 0);
+        assert!(install.involves("git"));
+        assert!(!install.involves("nodejs"));
+
+        let mut update_all = entry(2, JobKind::UpdateAll, &["*"], 0);
+        update_all.versions.insert("nodejs".to_string(), "20.0.0".to_string());
+        assert!(update_all.involves("NodeJS"));
+        assert!(!update_all.involves("git"));
+    }
+}
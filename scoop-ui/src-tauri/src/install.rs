@@ -1,273 +1,386 @@
-use serde::{Deserialize, Serialize};
-use std::process::Command;
-use std::os::windows::process::CommandExt;
-use tauri::{AppHandle, Emitter};
+use std::collections::VecDeque;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::{mpsc, Arc};
+use tauri::State;
 
-#[derive(Clone, Serialize)]
-pub struct InstallProgress {
-    pub app_name: String,
-    pub status: String,
-    pub progress: f32,
-    pub message: String,
-}
-
-#[derive(Clone, Serialize)]
-pub struct DependencyInfo {
-    pub app_name: String,
-    pub dependencies: Vec<String>,
-}
+use crate::error::AppError;
+use crate::jobs::{JobContext, JobInfo, JobKind, JobManager};
+use crate::manifest::{self, Manifest};
+use crate::manifest_index::ManifestIndex;
+use crate::paths::{self, Scope};
+use crate::scoop_command::{self, Architecture, InstallOptions, ScoopCommand};
+use crate::scoop_output::{self, ProgressTracker};
+use crate::versions;
 
 /// 检测应用的依赖项
+///
+/// 没有指定 bucket 时先从 manifest 索引中查找应用所在的 bucket。
 #[tauri::command]
-pub fn check_dependencies(app_name: String, bucket: Option<String>) -> Result<Vec<String>, String> {
-    use std::path::PathBuf;
-    
-    // 构建 manifest 文件路径
-    let manifest_path = if let Some(bucket_name) = bucket {
-        std::env::var_os("USERPROFILE")
-            .map(|home| {
-                PathBuf::from(home)
-                    .join("scoop")
-                    .join("buckets")
-                    .join(&bucket_name)
-                    .join("bucket")
-                    .join(format!("{}.json", app_name))
-            })
-            .ok_or("Failed to get USERPROFILE")?
-    } else {
-        // 如果没有指定 bucket,尝试在所有 bucket 中查找
-        let buckets_dir = std::env::var_os("USERPROFILE")
-            .map(|home| PathBuf::from(home).join("scoop").join("buckets"))
-            .ok_or("Failed to get USERPROFILE")?;
-        
-        let mut found_path = None;
-        if let Ok(entries) = std::fs::read_dir(&buckets_dir) {
-            for entry in entries.flatten() {
-                if entry.path().is_dir() {
-                    let test_path = entry.path()
-                        .join("bucket")
-                        .join(format!("{}.json", app_name));
-                    if test_path.exists() {
-                        found_path = Some(test_path);
-                        break;
-                    }
-                }
-            }
-        }
-        
-        found_path.ok_or(format!("Manifest not found for {}", app_name))?
-    };
-    
+pub fn check_dependencies(
+    index: State<'_, Arc<ManifestIndex>>,
+    app_name: String,
+    bucket: Option<String>,
+) -> Result<Vec<String>, AppError> {
+    let root = crate::scoop_root()?;
+    let bucket = bucket.or_else(|| index.find(&root, &app_name).map(|entry| entry.bucket));
+    read_dependencies(&root, &app_name, bucket.as_deref())
+}
+
+fn read_dependencies(root: &Path, app_name: &str, bucket: Option<&str>) -> Result<Vec<String>, AppError> {
+    let manifest_path = manifest_path(root, app_name, bucket)?;
     if !manifest_path.exists() {
         return Ok(Vec::new());
     }
     
-    // 读取并解析 manifest
-    let content = std::fs::read_to_string(&manifest_path)
-        .map_err(|e| format!("Failed to read manifest: {}", e))?;
-    
-    let json: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse manifest: {}", e))?;
+    Ok(Manifest::read(&manifest_path)?.depends)
+}
+
+fn manifest_path(root: &Path, app_name: &str, bucket: Option<&str>) -> Result<PathBuf, AppError> {
+    // 名字来自界面,先校验再拼接路径
+    scoop_command::validate_app(app_name)?;
+    if let Some(bucket) = bucket {
+        scoop_command::validate_bucket(bucket)?;
+    }
+    // 如果没有指定 bucket,尝试在所有 bucket 中查找
+    match bucket {
+        Some(bucket_name) => Ok(manifest::bucket_manifest_path(root, bucket_name, app_name)),
+        None => manifest::find_in_buckets(root, app_name)
+            .map(|(path, _)| path)
+            .ok_or(AppError::ManifestNotFound { app: app_name.to_string(), bucket: None }),
+    }
+}
+
+/// 读取 bucket 中的 manifest,用于安装前检查选项
+fn read_bucket_manifest(root: &Path, app_name: &str, bucket: Option<&str>) -> Result<Manifest, AppError> {
+    let manifest_path = manifest_path(root, app_name, bucket)?;
+    if !manifest_path.exists() {
+        return Err(AppError::ManifestNotFound { app: app_name.to_string(), bucket: bucket.map(str::to_string) });
+    }
     
-    // 提取依赖
-    let dependencies = match json.get("depends") {
-        Some(serde_json::Value::String(s)) => vec![s.clone()],
-        Some(serde_json::Value::Array(arr)) => {
-            arr.iter()
-                .filter_map(|v| v.as_str())
-                .map(|s| s.to_string())
-                .collect()
-        }
-        _ => Vec::new(),
+    Manifest::read(&manifest_path)
+}
+
+/// 安装指定版本前检查 manifest,返回需要提示用户的警告
+///
+/// 请求的就是 bucket 中的版本时按普通安装处理,返回 None。其他版本由 scoop 根据 `autoupdate`
+/// 生成 manifest,没有 `autoupdate` 时返回 [`AppError::VersionUnavailable`]。
+fn check_version(app_name: &str, manifest: &Manifest, version: &str) -> Result<Option<String>, AppError> {
+    if versions::compare(&manifest.version, version).is_eq() {
+        return Ok(None);
+    }
+    if manifest.autoupdate.is_none() {
+        return Err(AppError::VersionUnavailable { app: app_name.to_string(), version: version.to_string() });
+    }
+    Ok(Some(format!(
+        "{} {} 不是 bucket 中的版本({}),scoop 会用 autoupdate 生成它的 manifest 再安装。\
+        安装后应用固定使用这个生成的 manifest,不会再随 bucket 更新;下载地址或哈希也可能无法验证。",
+        app_name, version, manifest.version
+    )))
+}
+
+/// `--arch` 指定的架构必须出现在 manifest 的 `architecture` 中,没有 `architecture` 的应用与架构无关
+fn check_architecture(app_name: &str, manifest: &Manifest, arch: Architecture) -> Result<(), AppError> {
+    let Some(architecture) = &manifest.architecture else {
+        return Ok(());
     };
-    
-    Ok(dependencies)
+    if architecture.get(arch.as_str()).is_some() {
+        return Ok(());
+    }
+    Err(AppError::ArchitectureUnsupported {
+        app: app_name.to_string(),
+        arch: arch.as_str().to_string(),
+        supported: architecture.names().into_iter().map(str::to_string).collect(),
+    })
 }
 
 /// 安装应用
+///
+/// `scope` 默认为当前用户,全局范围需要管理员权限,没有权限时直接返回错误而不是让 scoop 失败。
+/// `scope` 或 `options.global` 任一为全局时全局安装。任务放入队列,进度通过 operation-event 发送。
+///
+/// 指定 `version` 时安装 `app@version`,先用 [`check_version`] 检查 manifest 能否生成该版本,
+/// 警告会作为第一行输出发送,并附在成功信息之后。指定架构时用 [`check_architecture`] 检查。
+/// 实际使用的选项记在成功信息中。
 #[tauri::command]
 pub fn install_app(
-    app_handle: AppHandle,
+    jobs: State<'_, JobManager>,
+    index: State<'_, Arc<ManifestIndex>>,
     app_name: String,
-) -> Result<String, String> {
-    let app_handle_clone = app_handle.clone();
-    let app_name_clone = app_name.clone();
-    
-    // 发送开始安装事件
-    let _ = app_handle.emit("install-progress", InstallProgress {
-        app_name: app_name.clone(),
-        status: "starting".to_string(),
-        progress: 0.0,
-        message: format!("开始安装 {}...", app_name),
-    });
+    scope: Option<Scope>,
+    version: Option<String>,
+    options: Option<InstallOptions>,
+) -> Result<JobInfo, AppError> {
+    let mut options = options.unwrap_or_default();
+    options.global |= scope == Some(Scope::Global);
+    let scope = options.scope();
+    paths::check_elevation(scope)?;
     
-    // 使用线程在后台执行安装
-    std::thread::spawn(move || {
-        // 模拟进度更新 (5秒内从0到100)
-        let total_steps = 50;
-        let step_duration = std::time::Duration::from_millis(100); // 100ms per step
-        
-        for i in 1..=total_steps {
-            std::thread::sleep(step_duration);
-            let progress = i as f32 / total_steps as f32;
-            let message = if progress < 0.3 {
-                format!("正在下载 {}...", app_name_clone)
-            } else if progress < 0.6 {
-                format!("正在解压 {}...", app_name_clone)
-            } else if progress < 0.9 {
-                format!("正在配置 {}...", app_name_clone)
-            } else {
-                format!("即将完成 {}...", app_name_clone)
-            };
-            
-            let _ = app_handle_clone.emit("install-progress", InstallProgress {
-                app_name: app_name_clone.clone(),
-                status: "installing".to_string(),
-                progress,
-                message,
-            });
+    // 先校验参数,再用应用名拼接 manifest 路径
+    scoop_command::validate_app(&app_name)?;
+    if let Some(version) = &version {
+        scoop_command::validate_version(version)?;
+    }
+    let mut warning = None;
+    if version.is_some() || options.arch.is_some() {
+        let root = crate::scoop_root()?;
+        let (bucket, name) = match app_name.split_once('/') {
+            Some((bucket, name)) => (Some(bucket.to_string()), name),
+            None => (index.find(&root, &app_name).map(|entry| entry.bucket), app_name.as_str()),
+        };
+        let manifest = read_bucket_manifest(&root, name, bucket.as_deref())?;
+        if let Some(arch) = options.arch {
+            check_architecture(name, &manifest, arch)?;
         }
-        
-        // 执行实际的安装命令
-        let output = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-Command",
-                &format!("scoop install {}", app_name_clone),
-            ])
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .output();
-        
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error_msg = String::from_utf8_lossy(&output.stderr).to_string();
-                    let _ = app_handle_clone.emit("install-progress", InstallProgress {
-                        app_name: app_name_clone.clone(),
-                        status: "error".to_string(),
-                        progress: 0.0,
-                        message: error_msg,
-                    });
-                } else {
-                    // 发送完成事件
-                    let _ = app_handle_clone.emit("install-progress", InstallProgress {
-                        app_name: app_name_clone.clone(),
-                        status: "completed".to_string(),
-                        progress: 1.0,
-                        message: format!("{} 安装成功!", app_name_clone),
-                    });
-                }
-            }
-            Err(e) => {
-                let _ = app_handle_clone.emit("install-progress", InstallProgress {
-                    app_name: app_name_clone.clone(),
-                    status: "error".to_string(),
-                    progress: 0.0,
-                    message: e.to_string(),
-                });
-            }
+        if let Some(version) = &version {
+            warning = check_version(name, &manifest, version)?;
+        }
+    }
+    // 请求的就是 bucket 中的版本时按普通安装处理
+    let version = version.filter(|_| warning.is_some());
+    let command = ScoopCommand::install_with(&app_name, version.as_deref(), &options)?;
+    
+    let job = jobs.submit(JobKind::Install, vec![app_name.clone()], scope, move |ctx| {
+        if let Some(warning) = &warning {
+            ctx.emit_output(warning, None, 0.0);
         }
+        ctx.run_scoop(&command)?;
+        Ok(install_message(&app_name, version.as_deref(), &options, warning.as_deref()))
     });
     
-    Ok("安装已开始".to_string())
+    Ok(job)
 }
 
-/// 卸载应用
-#[tauri::command]
-pub fn uninstall_app(
-    app_handle: AppHandle,
-    app_name: String,
-) -> Result<String, String> {
-    let app_handle_clone = app_handle.clone();
-    let app_name_clone = app_name.clone();
+/// 安装成功后的信息,附上使用的选项和安装指定版本的警告
+fn install_message(app_name: &str, version: Option<&str>, options: &InstallOptions, warning: Option<&str>) -> String {
+    let mut message = match version {
+        Some(version) => format!("{} {} 安装成功!", app_name, version),
+        None => format!("{} 安装成功!", app_name),
+    };
+    let flags = options.to_string();
+    if !flags.is_empty() {
+        message.push_str(&format!("\n安装选项:{}", flags));
+    }
+    if let Some(warning) = warning {
+        message.push('\n');
+        message.push_str(warning);
+    }
+    message
+}
+
+/// 把 scoop 子进程的每一行输出连同解析出的阶段作为任务的输出事件发送,直到进程退出
+///
+/// 进度由 `tracker` 根据解析出的阶段推算,只增不减。
+pub(crate) fn stream_scoop_output(ctx: &JobContext, child: Child, mut tracker: ProgressTracker) -> Result<(), AppError> {
+    run_streamed(ctx, child, |line| {
+        let phase = scoop_output::parse_line(line);
+        let progress = tracker.advance(phase.as_ref());
+        ctx.emit_output(line, phase, progress);
+    })
+}
+
+/// 逐行读取子进程的 stdout/stderr,每读到一行就回调 `on_line`
+///
+/// aria2 等下载器用 `\r` 刷新同一行,所以 `\r` 和 `\n` 都当作行尾处理。
+/// 进程以非零状态退出时返回 [`AppError::CommandFailed`],错误信息取 stderr(为空则取最后几行输出)。
+fn run_streamed(ctx: &JobContext, mut child: Child, mut on_line: impl FnMut(&str)) -> Result<(), AppError> {
+    let (tx, rx) = mpsc::channel::<(bool, String)>();
     
-    // 发送开始卸载事件
-    let _ = app_handle.emit("uninstall-progress", InstallProgress {
-        app_name: app_name.clone(),
-        status: "starting".to_string(),
-        progress: 0.0,
-        message: format!("开始卸载 {}...", app_name),
-    });
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        let tx = tx.clone();
+        readers.push(std::thread::spawn(move || read_lines(stdout, |line| {
+            let _ = tx.send((false, line));
+        })));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let tx = tx.clone();
+        readers.push(std::thread::spawn(move || read_lines(stderr, |line| {
+            let _ = tx.send((true, line));
+        })));
+    }
+    drop(tx);
     
-    std::thread::spawn(move || {
-        // 模拟进度更新 (5秒内从0到100)
-        let total_steps = 50;
-        let step_duration = std::time::Duration::from_millis(100);
-        
-        for i in 1..=total_steps {
-            std::thread::sleep(step_duration);
-            let progress = i as f32 / total_steps as f32;
-            let message = if progress < 0.5 {
-                format!("正在清理 {} 的文件...", app_name_clone)
-            } else {
-                format!("正在移除 {} 的配置...", app_name_clone)
-            };
-            
-            let _ = app_handle_clone.emit("uninstall-progress", InstallProgress {
-                app_name: app_name_clone.clone(),
-                status: "uninstalling".to_string(),
-                progress,
-                message,
-            });
+    let mut stderr_lines = Vec::new();
+    let mut tail: VecDeque<String> = VecDeque::new();
+    for (is_stderr, line) in rx {
+        on_line(&line);
+        if is_stderr {
+            stderr_lines.push(line.clone());
         }
-        
-        let output = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-Command",
-                &format!("scoop uninstall {}", app_name_clone),
-            ])
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .output();
-        
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error_msg = String::from_utf8_lossy(&output.stderr).to_string();
-                    let _ = app_handle_clone.emit("uninstall-progress", InstallProgress {
-                        app_name: app_name_clone.clone(),
-                        status: "error".to_string(),
-                        progress: 0.0,
-                        message: error_msg,
-                    });
-                } else {
-                    // 发送完成事件
-                    let _ = app_handle_clone.emit("uninstall-progress", InstallProgress {
-                        app_name: app_name_clone.clone(),
-                        status: "completed".to_string(),
-                        progress: 1.0,
-                        message: format!("{} 卸载成功!", app_name_clone),
-                    });
+        if tail.len() == 10 {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+    for reader in readers {
+        let _ = reader.join();
+    }
+    
+    let status = ctx.wait(&mut child)?;
+    if status.success() {
+        Ok(())
+    } else if !stderr_lines.is_empty() {
+        Err(AppError::exited(status.code(), &stderr_lines.join("\n")))
+    } else {
+        Err(AppError::exited(status.code(), &Vec::from(tail).join("\n")))
+    }
+}
+
+fn read_lines(mut reader: impl Read, mut on_line: impl FnMut(String)) {
+    let mut buf = [0u8; 4096];
+    let mut pending: Vec<u8> = Vec::new();
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        for &byte in &buf[..n] {
+            if byte == b'\n' || byte == b'\r' {
+                let line = String::from_utf8_lossy(&pending).trim_end().to_string();
+                if !line.is_empty() {
+                    on_line(line);
                 }
-            }
-            Err(e) => {
-                let _ = app_handle_clone.emit("uninstall-progress", InstallProgress {
-                    app_name: app_name_clone.clone(),
-                    status: "error".to_string(),
-                    progress: 0.0,
-                    message: e.to_string(),
-                });
+                pending.clear();
+            } else {
+                pending.push(byte);
             }
         }
+    }
+    let line = String::from_utf8_lossy(&pending).trim_end().to_string();
+    if !line.is_empty() {
+        on_line(line);
+    }
+}
+
+/// 卸载应用,`scope` 的处理与 [`install_app`] 相同
+#[tauri::command]
+pub fn uninstall_app(
+    jobs: State<'_, JobManager>,
+    app_name: String,
+    scope: Option<Scope>,
+) -> Result<JobInfo, AppError> {
+    let scope = scope.unwrap_or_default();
+    paths::check_elevation(scope)?;
+    
+    let command = ScoopCommand::uninstall(&app_name, scope)?;
+    let job = jobs.submit(JobKind::Uninstall, vec![app_name.clone()], scope, move |ctx| {
+        ctx.run_scoop(&command)?;
+        Ok(format!("{} 卸载成功!", app_name))
     });
     
-    Ok("卸载已开始".to_string())
+    Ok(job)
 }
 
-/// 检查应用是否已安装
+/// 检查应用是否已安装(当前用户或全局)
 #[tauri::command]
-pub fn is_app_installed(app_name: String) -> Result<bool, String> {
-    use std::path::PathBuf;
+pub fn is_app_installed(app_name: String) -> Result<bool, AppError> {
+    let installed_in = |root: PathBuf| root.join("apps").join(&app_name).join("current").exists();
     
-    let app_path = std::env::var_os("USERPROFILE")
-        .map(|home| {
-            PathBuf::from(home)
-                .join("scoop")
-                .join("apps")
-                .join(&app_name)
-                .join("current")
-        })
-        .ok_or("Failed to get USERPROFILE")?;
+    let root = crate::scoop_root()?;
+    Ok(installed_in(root) || paths::global_root().is_ok_and(installed_in))
+}
+
+/// 找出 scoop 被中断后在应用目录下留下的未装完的版本目录(没有 install.json 的目录)
+pub(crate) fn find_incomplete_installs(root: &Path, app_name: &str) -> Vec<String> {
+    let app_dir = root.join("apps").join(app_name);
+    let Ok(entries) = std::fs::read_dir(&app_dir) else { return Vec::new() };
+    let versions: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_name() != "current")
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
     
-    Ok(app_path.exists())
+    // scoop 自身的 current 是一个普通目录,没有版本目录
+    if versions.is_empty() && !app_dir.join("current").exists() {
+        return vec![app_dir.to_string_lossy().to_string()];
+    }
+    versions.into_iter()
+        .filter(|version_dir| !version_dir.join("install.json").exists())
+        .map(|version_dir| version_dir.to_string_lossy().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixture_root;
+
+    #[test]
+    fn reads_dependencies_from_manifest() {
+        let root = fixture_root();
+        assert_eq!(read_dependencies(&root, "vscode", Some("extras")).unwrap(), ["7zip"]);
+        // 未指定 bucket 时在所有 bucket 中查找
+        assert_eq!(read_dependencies(&root, "vscode", None).unwrap(), ["7zip"]);
+        assert!(read_dependencies(&root, "git", Some("main")).unwrap().is_empty());
+        assert!(read_dependencies(&root, "vscode", Some("main")).unwrap().is_empty());
+        assert!(read_dependencies(&root, "no-such-app", None).is_err());
+        for (app, bucket) in [("vscode", Some("../../..")), ("../extras/bucket/vscode", None), ("..", Some("extras"))] {
+            let error = read_dependencies(&root, app, bucket).unwrap_err();
+            assert_eq!(error.code(), "invalid_argument", "{} {:?}", app, bucket);
+        }
+    }
+
+    #[test]
+    fn checks_that_a_version_can_be_generated() {
+        let root = fixture_root();
+        let nodejs = read_bucket_manifest(&root, "nodejs", Some("main")).unwrap();
+        let sevenzip = read_bucket_manifest(&root, "7zip", None).unwrap();
+        // bucket 中的版本直接安装
+        assert_eq!(check_version("nodejs", &nodejs, "21.6.1").unwrap(), None);
+        assert_eq!(check_version("7zip", &sevenzip, "23.01").unwrap(), None);
+
+        let warning = check_version("nodejs", &nodejs, "18.19.0").unwrap().unwrap();
+        assert!(warning.contains("18.19.0") && warning.contains("21.6.1"));
+
+        assert_eq!(
+            check_version("7zip", &sevenzip, "22.01"),
+            Err(AppError::VersionUnavailable { app: "7zip".to_string(), version: "22.01".to_string() })
+        );
+        assert!(matches!(
+            read_bucket_manifest(&root, "vscode", Some("main")),
+            Err(AppError::ManifestNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn checks_the_requested_architecture() {
+        let root = fixture_root();
+        let nodejs = read_bucket_manifest(&root, "nodejs", Some("main")).unwrap();
+        assert!(check_architecture("nodejs", &nodejs, Architecture::X86).is_ok());
+        assert_eq!(
+            check_architecture("nodejs", &nodejs, Architecture::Arm64),
+            Err(AppError::ArchitectureUnsupported {
+                app: "nodejs".to_string(),
+                arch: "arm64".to_string(),
+                supported: vec!["64bit".to_string(), "32bit".to_string()],
+            })
+        );
+        // 没有 architecture 块的应用与架构无关
+        let git = read_bucket_manifest(&root, "git", Some("main")).unwrap();
+        assert!(check_architecture("git", &git, Architecture::Arm64).is_ok());
+
+        let options = InstallOptions { arch: Some(Architecture::X86), no_cache: true, ..Default::default() };
+        assert_eq!(
+            install_message("nodejs", None, &options, None),
+            "nodejs 安装成功!\n安装选项:--arch 32bit --no-cache"
+        );
+        assert_eq!(install_message("git", None, &InstallOptions::default(), None), "git 安装成功!");
+    }
+
+    #[test]
+    fn finds_incomplete_installs() {
+        let root = fixture_root();
+        let leftovers = find_incomplete_installs(&root, "nodejs");
+        assert_eq!(leftovers.len(), 1);
+        assert!(leftovers[0].ends_with("21.6.1"));
+        assert!(find_incomplete_installs(&root, "7zip").is_empty());
+        assert!(find_incomplete_installs(&root, "not-installed").is_empty());
+
+        let removed = find_incomplete_installs(&root, "half-removed");
+        assert_eq!(removed.len(), 1);
+        assert!(removed[0].ends_with("half-removed"));
+    }
 }
@@ -0,0 +1,575 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+
+use crate::error::AppError;
+use crate::events::{OperationEvent, OPERATION_EVENT, SCHEMA_VERSION};
+use crate::history::{History, HistoryEntry};
+use crate::install;
+use crate::paths::Scope;
+use crate::process;
+use crate::scoop_command::{self, ScoopCommand};
+use crate::scoop_output::{ProgressTracker, ScoopPhase};
+
+/// 已结束的任务最多保留多少条,超出后丢弃最早结束的
+const MAX_FINISHED_JOBS: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Install,
+    Uninstall,
+    Update,
+    UpdateAll,
+    UpdateScoop,
+    AddBucket,
+    RemoveBucket,
+    CheckUpdates,
+    Hold,
+    Unhold,
+}
+
+impl JobKind {
+    /// 是否会修改 ~/scoop 目录。修改类任务必须串行执行,只读任务可以并行
+    pub fn is_mutation(self) -> bool {
+        !matches!(self, JobKind::CheckUpdates)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Serialize)]
+pub struct JobInfo {
+    pub id: u64,
+    pub kind: JobKind,
+    pub targets: Vec<String>,
+    pub scope: Scope,
+    pub status: JobStatus,
+    pub created_at: u64,          // Unix timestamp in milliseconds
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub message: Option<String>,  // 成功时为 scoop 输出,失败时为错误信息
+    /// 失败时的错误,与命令直接返回的错误格式相同
+    pub error: Option<AppError>,
+    /// 任务被取消后 scoop 留下的未装完的应用目录,需要用户手动清理
+    pub leftover_dirs: Vec<String>,
+    /// 批量任务中每个应用的结果
+    pub app_results: Vec<AppResult>,
+}
+
+/// 批量任务中单个应用的结果
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AppResult {
+    pub app: String,
+    pub outcome: AppOutcome,
+    /// 失败时的错误信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppOutcome {
+    Updated,
+    /// 已用 `scoop hold` 锁定,没有更新
+    SkippedHeld,
+    Failed,
+}
+
+/// 任务运行过程中收集的信息,结束时写入操作历史
+#[derive(Clone, Default)]
+struct JobRecord {
+    commands: Vec<String>,
+    exit_code: Option<i32>,
+    output: Vec<String>,
+    versions: BTreeMap<String, String>,
+    app_results: Vec<AppResult>,
+}
+
+/// 传给任务函数的上下文,用于发送输出事件,并记录任务正在运行的子进程以便取消
+pub struct JobContext {
+    pub id: u64,
+    pub kind: JobKind,
+    pub targets: Vec<String>,
+    pub scope: Scope,
+    created_at: u64,
+    started_at: AtomicU64,  // 0 表示尚未开始
+    app_handle: AppHandle,
+    cancelled: AtomicBool,
+    child_pid: Mutex<Option<u32>>,
+    record: Mutex<JobRecord>,
+    /// 任务函数,由工作线程或 [`JobManager::cancel`] 取走,先取走的一方决定任务是执行还是直接取消
+    job: Mutex<Option<JobFn>>,
+}
+
+impl JobContext {
+    fn new(info: &JobInfo, app_handle: AppHandle, job: JobFn) -> Self {
+        Self {
+            id: info.id,
+            kind: info.kind,
+            targets: info.targets.clone(),
+            scope: info.scope,
+            created_at: info.created_at,
+            started_at: AtomicU64::new(0),
+            app_handle,
+            cancelled: AtomicBool::new(false),
+            child_pid: Mutex::new(None),
+            record: Mutex::new(JobRecord::default()),
+            job: Mutex::new(Some(job)),
+        }
+    }
+
+    fn take_job(&self) -> Option<JobFn> {
+        self.job.lock().unwrap().take()
+    }
+
+    /// 以当前任务信息为基础构造一个事件
+    fn event(&self, status: JobStatus) -> OperationEvent {
+        let started_at = self.started_at.load(Ordering::SeqCst);
+        OperationEvent {
+            schema_version: SCHEMA_VERSION,
+            job_id: self.id,
+            operation: self.kind,
+            targets: self.targets.clone(),
+            status,
+            phase: None,
+            progress: None,
+            bytes_total: None,
+            line: None,
+            message: None,
+            error: None,
+            leftover_dirs: Vec::new(),
+            app_results: Vec::new(),
+            created_at: self.created_at,
+            started_at: (started_at != 0).then_some(started_at),
+            timestamp: now_millis(),
+        }
+    }
+
+    fn emit(&self, event: OperationEvent) {
+        let _ = self.app_handle.emit(OPERATION_EVENT, event);
+    }
+
+    /// 转发一行 scoop 输出,同时记入操作历史
+    pub fn emit_output(&self, line: &str, phase: Option<ScoopPhase>, progress: f32) {
+        {
+            let mut record = self.record.lock().unwrap();
+            record.output.push(line.to_string());
+            match &phase {
+                Some(ScoopPhase::Installed { app, version })
+                | Some(ScoopPhase::AlreadyLatest { app, version }) => {
+                    record.versions.insert(app.clone(), version.clone());
+                }
+                _ => {}
+            }
+        }
+        let mut event = self.event(JobStatus::Running);
+        if let Some(ScoopPhase::Downloading { total_bytes, .. }) = &phase {
+            event.bytes_total = *total_bytes;
+        }
+        event.phase = phase;
+        event.progress = Some(progress);
+        event.line = Some(line.to_string());
+        self.emit(event);
+    }
+
+    /// 记录批量任务中一个应用的结果
+    pub fn record_app_result(&self, app: &str, outcome: AppOutcome, message: Option<String>) {
+        self.record.lock().unwrap().app_results.push(AppResult {
+            app: app.to_string(),
+            outcome,
+            message,
+        });
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// 启动子进程并登记其 PID。任务已被取消时不再启动
+    pub fn spawn(&self, command: &mut Command) -> std::io::Result<Child> {
+        let mut pid = self.child_pid.lock().unwrap();
+        if self.is_cancelled() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "operation cancelled"));
+        }
+        self.record.lock().unwrap().commands.push(scoop_command::describe(command));
+        let child = command.spawn()?;
+        *pid = Some(child.id());
+        Ok(child)
+    }
+
+    /// 等待子进程退出并记录退出码
+    pub fn wait(&self, child: &mut Child) -> std::io::Result<ExitStatus> {
+        let status = child.wait();
+        self.forget_child();
+        let status = status?;
+        self.record.lock().unwrap().exit_code = status.code();
+        Ok(status)
+    }
+
+    /// 子进程已退出,之后取消时不能再结束这个 PID,它可能已经被其他进程复用
+    fn forget_child(&self) {
+        *self.child_pid.lock().unwrap() = None;
+    }
+
+    /// 与 [`Command::output`] 相同,但子进程可以被取消,输出和退出码会记入操作历史
+    pub fn output(&self, command: &mut Command) -> std::io::Result<Output> {
+        let output = self.spawn(command.stdout(Stdio::piped()).stderr(Stdio::piped()))?
+            .wait_with_output();
+        self.forget_child();
+        let output = output?;
+        let mut record = self.record.lock().unwrap();
+        record.exit_code = output.status.code();
+        for stream in [&output.stdout, &output.stderr] {
+            record.output.extend(String::from_utf8_lossy(stream).lines().map(str::to_string));
+        }
+        Ok(output)
+    }
+
+    /// 运行一条 scoop 命令,把每一行输出作为进度事件转发,直到进程退出
+    pub fn run_scoop(&self, command: &ScoopCommand) -> Result<(), AppError> {
+        self.run_scoop_step(command, ProgressTracker::new())
+    }
+
+    /// 批量任务中的一条 scoop 命令,进度由 `tracker` 换算到它在整个任务中的区间
+    pub fn run_scoop_step(&self, command: &ScoopCommand, tracker: ProgressTracker) -> Result<(), AppError> {
+        let child = self.spawn(command.to_command().stdout(Stdio::piped()).stderr(Stdio::piped()))?;
+        install::stream_scoop_output(self, child, tracker)
+    }
+
+    /// 运行一条输出很短的 scoop 命令(如 `scoop bucket add`),成功时返回其 stdout
+    pub fn run_scoop_quiet(&self, command: &ScoopCommand) -> Result<String, AppError> {
+        let output = self.output(&mut command.to_command())?;
+        if !output.status.success() {
+            return Err(AppError::command_failed(&output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn cancel(&self) {
+        let pid = self.child_pid.lock().unwrap();
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(pid) = *pid {
+            process::kill_process_tree(pid);
+        }
+    }
+}
+
+type JobFn = Box<dyn FnOnce(&JobContext) -> Result<String, AppError> + Send>;
+
+struct JobTable {
+    jobs: Vec<JobInfo>,
+    contexts: HashMap<u64, Arc<JobContext>>,
+}
+
+/// 后台任务队列,放在 Tauri managed state 中
+///
+/// 所有修改 scoop 目录的命令都通过 [`JobManager::submit`] 提交,由唯一的工作线程按顺序执行,
+/// 避免两个 scoop 进程同时操作同一个目录。只读任务不进入队列,直接在新线程中执行。
+pub struct JobManager {
+    app_handle: AppHandle,
+    history: Arc<History>,
+    table: Arc<Mutex<JobTable>>,
+    next_id: AtomicU64,
+    queue: mpsc::Sender<Arc<JobContext>>,
+}
+
+impl JobManager {
+    /// 任务编号接着历史中最大的编号,保证重启后 ID 不重复
+    pub fn new(app_handle: AppHandle, history: Arc<History>) -> Self {
+        let table = Arc::new(Mutex::new(JobTable {
+            jobs: Vec::new(),
+            contexts: HashMap::new(),
+        }));
+        let (queue, receiver) = mpsc::channel::<Arc<JobContext>>();
+
+        let worker_table = table.clone();
+        let worker_history = history.clone();
+        std::thread::spawn(move || {
+            for ctx in receiver {
+                execute(&worker_table, &worker_history, &ctx);
+            }
+        });
+
+        let next_id = history.last_job_id() + 1;
+        Self {
+            app_handle,
+            history,
+            table,
+            next_id: AtomicU64::new(next_id),
+            queue,
+        }
+    }
+
+    /// 提交一个任务,立即返回其排队时的信息
+    pub fn submit<F>(&self, kind: JobKind, targets: Vec<String>, scope: Scope, run: F) -> JobInfo
+    where
+        F: FnOnce(&JobContext) -> Result<String, AppError> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let info = JobInfo {
+            id,
+            kind,
+            targets,
+            scope,
+            status: JobStatus::Queued,
+            created_at: now_millis(),
+            started_at: None,
+            finished_at: None,
+            message: None,
+            error: None,
+            leftover_dirs: Vec::new(),
+            app_results: Vec::new(),
+        };
+        let ctx = Arc::new(JobContext::new(&info, self.app_handle.clone(), Box::new(run)));
+        ctx.emit(ctx.event(JobStatus::Queued));
+
+        {
+            let mut table = self.table.lock().unwrap();
+            table.jobs.push(info.clone());
+            table.contexts.insert(id, ctx.clone());
+            prune_finished(&mut table.jobs);
+        }
+
+        if kind.is_mutation() {
+            let _ = self.queue.send(ctx);
+        } else {
+            let table = self.table.clone();
+            let history = self.history.clone();
+            std::thread::spawn(move || execute(&table, &history, &ctx));
+        }
+
+        info
+    }
+
+    /// 请求取消任务:排队中的任务立即结束为已取消,运行中的任务结束其整个进程树
+    pub fn cancel(&self, id: u64) -> Result<JobInfo, AppError> {
+        let ctx = {
+            let table = self.table.lock().unwrap();
+            let info = table.jobs.iter().find(|job| job.id == id).ok_or(AppError::JobNotFound(id))?;
+            if info.finished_at.is_some() {
+                return Err(AppError::JobFinished(id));
+            }
+            table.contexts.get(&id).cloned()
+        };
+        if let Some(ctx) = ctx {
+            ctx.cancel();
+            // 工作线程还没有取走任务函数,说明任务仍在排队,不必等它排到
+            if let Some(job) = ctx.take_job() {
+                drop(job);
+                finish(&self.table, &self.history, &ctx, None);
+            }
+        }
+        self.get(id).ok_or(AppError::JobNotFound(id))
+    }
+
+    /// 提交任务并等待其结束,返回任务本身的结果
+    pub async fn run<F>(&self, kind: JobKind, targets: Vec<String>, scope: Scope, run: F) -> Result<String, AppError>
+    where
+        F: FnOnce(&JobContext) -> Result<String, AppError> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        self.submit(kind, targets, scope, move |ctx| {
+            let result = run(ctx);
+            let _ = tx.send(result.clone());
+            result
+        });
+
+        // 任务在排队时被取消的话 `run` 不会执行,发送端随任务函数一起被丢弃
+        tauri::async_runtime::spawn_blocking(move || rx.recv())
+            .await
+            .map_err(|e| AppError::Io { path: None, message: e.to_string() })?
+            .map_err(|_| AppError::Cancelled)?
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.table.lock().unwrap().jobs.clone()
+    }
+
+    pub fn get(&self, id: u64) -> Option<JobInfo> {
+        find(&self.table, id)
+    }
+
+    /// 尚未结束的任务目前为止的输出
+    pub fn running_output(&self, id: u64) -> Option<Vec<String>> {
+        let table = self.table.lock().unwrap();
+        let ctx = table.contexts.get(&id)?;
+        let output = ctx.record.lock().unwrap().output.clone();
+        Some(output)
+    }
+}
+
+fn execute(table: &Mutex<JobTable>, history: &History, ctx: &JobContext) {
+    // 排队期间被取消的任务已经由 `JobManager::cancel` 结束
+    let Some(job) = ctx.take_job() else { return };
+    let result = if ctx.is_cancelled() {
+        // 排队期间就被取消了
+        None
+    } else {
+        let started_at = now_millis();
+        ctx.started_at.store(started_at, Ordering::SeqCst);
+        update(table, ctx.id, |info| {
+            info.status = JobStatus::Running;
+            info.started_at = Some(started_at);
+        });
+        ctx.emit(ctx.event(JobStatus::Running));
+        Some(job(ctx))
+    };
+    finish(table, history, ctx, result);
+}
+
+/// 更新任务状态、写入历史并发送终态事件。`result` 为 None 表示任务没有开始执行就被取消
+fn finish(table: &Mutex<JobTable>, history: &History, ctx: &JobContext, result: Option<Result<String, AppError>>) {
+    // 先写入历史再移除上下文,保证运行中和结束后都能查到输出。
+    // 只持有 record 的锁复制一份:`running_output` 先锁 table 再锁 record,这里不能反过来
+    let record = ctx.record.lock().unwrap().clone();
+
+    // 被取消时检查 scoop 是否留下了未装完的目录。没有开始执行的任务不会留下目录,已有的残留与它无关
+    let leftover_dirs = match ctx.scope.root() {
+        Ok(root) if result.is_some() && ctx.is_cancelled() => {
+            let apps: Vec<&str> = match ctx.kind {
+                // 目标可以带 `bucket/` 前缀,应用目录只用应用名
+                JobKind::Install | JobKind::Update => ctx.targets.iter()
+                    .map(|target| target.rsplit('/').next().unwrap_or(target))
+                    .collect(),
+                // 批量更新只检查已经开始更新的应用,被中断的那个会记为失败
+                JobKind::UpdateAll => record.app_results.iter()
+                    .filter(|result| result.outcome != AppOutcome::SkippedHeld)
+                    .map(|result| result.app.as_str())
+                    .collect(),
+                _ => Vec::new(),
+            };
+            apps.into_iter().flat_map(|app| install::find_incomplete_installs(&root, app)).collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let (status, mut message, error) = match result {
+        _ if ctx.is_cancelled() => (JobStatus::Cancelled, None, None),
+        Some(Ok(message)) => (JobStatus::Completed, Some(message), None),
+        Some(Err(error)) => (JobStatus::Failed, Some(error.to_string()), Some(error)),
+        None => (JobStatus::Cancelled, None, None),
+    };
+
+    let finished_at = now_millis();
+    let started_at = ctx.started_at.load(Ordering::SeqCst);
+    let entry = HistoryEntry {
+        job_id: ctx.id,
+        operation: ctx.kind,
+        targets: ctx.targets.clone(),
+        scope: ctx.scope,
+        status,
+        commands: record.commands.clone(),
+        exit_code: record.exit_code,
+        created_at: ctx.created_at,
+        started_at: (started_at != 0).then_some(started_at),
+        finished_at,
+        message: message.clone(),
+        versions: record.versions.clone(),
+        leftover_dirs: leftover_dirs.clone(),
+        app_results: record.app_results.clone(),
+    };
+    // 历史只记录修改 ~/scoop 的操作,检查更新之类的只读任务不写入。
+    // 写入失败不影响任务结果,只在结果信息中说明
+    if ctx.kind.is_mutation() {
+        if let Err(e) = history.append(&entry, &record.output) {
+            let note = format!("操作历史没有保存:{}", e);
+            message = Some(match message {
+                Some(message) => format!("{}\n{}", message, note),
+                None => note,
+            });
+        }
+    }
+    update(table, ctx.id, |info| {
+        info.finished_at = Some(finished_at);
+        info.status = status;
+        info.message = message.clone();
+        info.error = error.clone();
+        info.leftover_dirs = leftover_dirs.clone();
+        info.app_results = record.app_results.clone();
+    });
+    table.lock().unwrap().contexts.remove(&ctx.id);
+
+    let mut event = ctx.event(status);
+    event.progress = (status == JobStatus::Completed).then_some(1.0);
+    event.message = message;
+    event.error = error;
+    event.leftover_dirs = leftover_dirs;
+    event.app_results = record.app_results;
+    ctx.emit(event);
+}
+
+fn find(table: &Mutex<JobTable>, id: u64) -> Option<JobInfo> {
+    table.lock().unwrap().jobs.iter().find(|job| job.id == id).cloned()
+}
+
+fn update(table: &Mutex<JobTable>, id: u64, f: impl FnOnce(&mut JobInfo)) {
+    if let Some(info) = table.lock().unwrap().jobs.iter_mut().find(|job| job.id == id) {
+        f(info);
+    }
+}
+
+fn prune_finished(jobs: &mut Vec<JobInfo>) {
+    let finished = jobs.iter().filter(|job| job.finished_at.is_some()).count();
+    let mut excess = finished.saturating_sub(MAX_FINISHED_JOBS);
+    jobs.retain(|job| {
+        if excess > 0 && job.finished_at.is_some() {
+            excess -= 1;
+            false
+        } else {
+            true
+        }
+    });
+}
+
+pub(crate) fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// 列出当前队列中和最近结束的任务
+#[tauri::command]
+pub fn list_jobs(jobs: tauri::State<'_, JobManager>) -> Vec<JobInfo> {
+    jobs.list()
+}
+
+/// 获取单个任务的状态
+#[tauri::command]
+pub fn get_job(jobs: tauri::State<'_, JobManager>, job_id: u64) -> Result<JobInfo, AppError> {
+    jobs.get(job_id).ok_or(AppError::JobNotFound(job_id))
+}
+
+/// 取消排队中或正在运行的任务,等待其结束后返回最终状态(包括残留的未装完目录)
+#[tauri::command]
+pub async fn cancel_operation(jobs: tauri::State<'_, JobManager>, job_id: u64) -> Result<JobInfo, AppError> {
+    jobs.cancel(job_id)?;
+
+    let table = jobs.table.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        // taskkill 之后子进程通常很快退出,最多等 30 秒
+        for _ in 0..300 {
+            match find(&table, job_id) {
+                Some(info) if info.finished_at.is_some() => return Ok(info),
+                Some(_) => std::thread::sleep(std::time::Duration::from_millis(100)),
+                None => break,
+            }
+        }
+        Err(AppError::JobStillRunning(job_id))
+    })
+    .await
+    .map_err(|e| AppError::Io { path: None, message: e.to_string() })?
+}
@@ -1,9 +1,24 @@
 use serde::{Deserialize, Serialize};
-use std::process::Command;
-use std::os::windows::process::CommandExt;
-use tauri::Emitter;
+use std::path::{Path, PathBuf};
+use tauri::{Emitter, Manager};
 
+use error::AppError;
+
+mod catalogue;
+mod error;
+mod events;
+mod history;
 mod install;
+mod jobs;
+mod manifest;
+mod manifest_index;
+mod paths;
+mod process;
+mod scoop_command;
+mod scoop_output;
+mod search;
+mod updates;
+mod versions;
 
 #[derive(Serialize, Deserialize, Clone)]
 struct ScoopBucket {
@@ -31,31 +46,77 @@ struct ScoopApp {
     updated: i64,           // Unix timestamp in milliseconds
     has_update: bool,       // Whether an update is available
     install_size: u64,      // Size in bytes
+    scope: paths::Scope,    // Installed for the current user or globally (-g)
+    architecture: Option<String>,   // 64bit, 32bit or arm64, from install.json
+    url: Option<String>,    // Manifest URL for apps installed from a URL instead of a bucket
+    held: bool,             // Pinned with `scoop hold`
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize)]
 struct SearchResult {
     name: String,
     version: String,
     description: String,
     bucket: String,
+    homepage: String,
+    bins: Vec<String>,
+    score: u32,
+    highlights: Vec<search::Highlight>,
+    installed: bool,
+    has_update: bool,
+    // False for apps found in the offline catalogue of a recommended bucket that is not added
+    bucket_added: bool,
+    // Where to add the bucket from, only when it is not added
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bucket_url: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+// Largest page the local search returns, to avoid UI lag
+const MAX_SEARCH_PAGE: usize = 200;
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SearchRequest {
+    // An empty query lists every app that passes the facets
+    query: String,
+    facets: search::Facets,
+    offset: usize,
+    limit: Option<usize>,
+    max_typos: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct LocalSearchResponse {
+    results: Vec<SearchResult>,
+    // Number of matches across all pages
+    total: usize,
+    offset: usize,
+    // "Did you mean" app names, only when nothing matched exactly
+    suggestions: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
 struct UpdatesCheckedPayload {
+    // Apps `scoop update` would update: outdated and not held
     updatable_apps: Vec<String>,
+    // Installed apps that are outdated or need attention, as listed by `scoop status`
+    apps: Vec<updates::AppStatus>,
 }
 
 #[tauri::command]
-fn get_buckets() -> Result<Vec<ScoopBucket>, String> {
-    use std::path::PathBuf;
-    
-    let buckets_dir = std::env::var_os("USERPROFILE")
-        .map(|home| PathBuf::from(home).join("scoop").join("buckets"))
-        .ok_or("Failed to get USERPROFILE")?;
+fn get_buckets() -> Result<Vec<ScoopBucket>, AppError> {
+    Ok(list_buckets(&scoop_root()?))
+}
 
+// The Scoop root for commands: failing to locate it means Scoop is not set up
+fn scoop_root() -> Result<PathBuf, AppError> {
+    paths::scoop_root().map_err(AppError::ScoopNotFound)
+}
+
+fn list_buckets(root: &Path) -> Vec<ScoopBucket> {
+    let buckets_dir = root.join("buckets");
     if !buckets_dir.exists() {
-        return Ok(Vec::new());
+        return Vec::new();
     }
 
     let mut buckets = Vec::new();
@@ -94,7 +155,7 @@ fn get_buckets() -> Result<Vec<ScoopBucket>, String> {
                 }
                 
                 // Get updated time (last modified of the folder or .git/FETCH_HEAD?)
-                let updated = if let Ok(metadata) = std::fs::metadata(&entry.path()) {
+                let updated = if let Ok(metadata) = std::fs::metadata(entry.path()) {
                     metadata.modified()
                         .ok()
                         .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
@@ -113,313 +174,230 @@ fn get_buckets() -> Result<Vec<ScoopBucket>, String> {
         }
     }
     
-    Ok(buckets)
+    buckets
 }
 
 #[tauri::command]
-fn add_bucket(name: String, url: Option<String>) -> Result<String, String> {
-    let mut args = vec!["-NoProfile", "-Command", "scoop", "bucket", "add", &name];
-    if let Some(ref u) = url {
-        args.push(u);
-    }
-
-    let output = Command::new("powershell")
-        .args(&args)
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| e.to_string())?;
-
-    if !output.status.success() {
-        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+async fn add_bucket(
+    jobs: tauri::State<'_, jobs::JobManager>,
+    name: String,
+    url: Option<String>,
+) -> Result<String, AppError> {
+    let command = scoop_command::ScoopCommand::bucket_add(&name, url.as_deref())?;
+    if scoop_root()?.join("buckets").join(&name).is_dir() {
+        return Err(AppError::BucketExists(name));
     }
-
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    jobs.run(jobs::JobKind::AddBucket, vec![name], paths::Scope::User, move |ctx| {
+        ctx.run_scoop_quiet(&command)
+    })
+    .await
 }
 
 #[tauri::command]
-fn remove_bucket(name: String) -> Result<String, String> {
-    let output = Command::new("powershell")
-        .args(["-NoProfile", "-Command", "scoop", "bucket", "rm", &name])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| e.to_string())?;
-
-    if !output.status.success() {
-        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+async fn remove_bucket(jobs: tauri::State<'_, jobs::JobManager>, name: String) -> Result<String, AppError> {
+    let command = scoop_command::ScoopCommand::bucket_rm(&name)?;
+    if !scoop_root()?.join("buckets").join(&name).is_dir() {
+        return Err(AppError::BucketNotFound(name));
     }
-
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    jobs.run(jobs::JobKind::RemoveBucket, vec![name], paths::Scope::User, move |ctx| {
+        ctx.run_scoop_quiet(&command)
+    })
+    .await
 }
 
 #[tauri::command]
-fn search_apps(query: String) -> Result<Vec<SearchResult>, String> {
-    // `scoop search <query>` converted to JSON to avoid brittle text parsing.
-    let script = r#"& {
-        param([string]$Query)
-        $lines = scoop search $Query
-        $results = foreach ($line in $lines) {
-            $trimmed = $line.Trim()
-            if (-not $trimmed) { continue }
-            if ($trimmed -match "^Results from" -or $trimmed -match "^'.*' bucket:") { continue }
-            if ($trimmed -match "^(?<name>\\S+)\\s+\\((?<version>[^)]+)\\)\\s+<(?<bucket>[^>]+)>\\s*(?<description>.*)$") {
-                [pscustomobject]@{
-                    name = $Matches.name
-                    version = $Matches.version
-                    bucket = $Matches.bucket
-                    description = $Matches.description
-                }
-                continue
-            }
-            if ($trimmed -match "^(?<name>\\S+)\\s+<(?<bucket>[^>]+)>\\s*(?<description>.*)$") {
-                [pscustomobject]@{
-                    name = $Matches.name
-                    version = ""
-                    bucket = $Matches.bucket
-                    description = $Matches.description
-                }
-            }
+async fn search_apps(
+    index: tauri::State<'_, std::sync::Arc<manifest_index::ManifestIndex>>,
+    catalogue: tauri::State<'_, std::sync::Arc<catalogue::Catalogue>>,
+    generations: tauri::State<'_, std::sync::Arc<search::Generations>>,
+    query: String,
+    include_not_added: Option<bool>,
+    request_id: Option<u64>,
+) -> Result<Vec<SearchResult>, AppError> {
+    let query = search::validate_query(&query)?;
+    let index = index.inner().clone();
+    let catalogue = catalogue.inner().clone();
+    run_search(&generations, search::SearchChannel::AllBuckets, request_id, move |superseded| {
+        let root = scoop_root()?;
+        let entries = index.entries(&root);
+        if superseded() {
+            return Err(AppError::Cancelled);
         }
-        $results = @($results)
-        $results | ConvertTo-Json -Compress
-    }"#;
-
-    let output = Command::new("powershell")
-        .args(["-NoProfile", "-Command", script, "-Args", &query])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| e.to_string())?;
-
-    if !output.status.success() {
-        return Err(String::from_utf8_lossy(&output.stderr).to_string());
-    }
+        let installed = installed_versions(&root, paths::global_root().ok().as_deref());
+        Ok(search_all_buckets(&entries, &catalogue, &installed, &query, include_not_added.unwrap_or(true)))
+    })
+    .await
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stdout_trimmed = stdout.trim();
-    if stdout_trimmed.is_empty() {
-        return Ok(Vec::new());
+// Runs a search on a blocking thread. With a request id, the search fails with `Cancelled`
+// as soon as a newer request id has started on the same channel, so only the latest search
+// of each kind returns results.
+async fn run_search<T, F>(
+    generations: &std::sync::Arc<search::Generations>,
+    channel: search::SearchChannel,
+    request_id: Option<u64>,
+    search: F,
+) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn Fn() -> bool) -> Result<T, AppError> + Send + 'static,
+{
+    if let Some(id) = request_id {
+        if !generations.begin(channel, id) {
+            return Err(AppError::Cancelled);
+        }
     }
+    let generations = generations.clone();
+    let superseded = move || request_id.is_some_and(|id| generations.is_superseded(channel, id));
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = search(&superseded)?;
+        if superseded() {
+            return Err(AppError::Cancelled);
+        }
+        Ok(result)
+    })
+    .await
+    .map_err(|e| AppError::Io { path: None, message: e.to_string() })?
+}
 
-    serde_json::from_str::<Vec<SearchResult>>(stdout_trimmed)
-        .map_err(|e| format!("Failed to parse search results JSON: {e}. Output: {stdout_trimmed}"))
+// Native `scoop search`: the local index, plus recommended buckets that are not added yet
+fn search_all_buckets(
+    entries: &[manifest_index::IndexEntry],
+    catalogue: &catalogue::Catalogue,
+    installed: &std::collections::HashMap<String, String>,
+    query: &str,
+    include_not_added: bool,
+) -> Vec<SearchResult> {
+    let added: std::collections::HashSet<String> = entries.iter().map(|entry| entry.bucket.clone()).collect();
+    let catalogued = if include_not_added { catalogue.entries() } else { std::sync::Arc::default() };
+    let not_added = catalogued.iter().filter(|entry| !added.contains(&entry.bucket));
+    let recommended = catalogue::recommended_buckets();
+    
+    let outcome = search::search(entries.iter().chain(not_added), query, &search::SearchOptions::default());
+    outcome.hits
+        .into_iter()
+        .map(|hit| {
+            let mut result = search_result(hit, installed);
+            if !added.contains(&result.bucket) {
+                result.bucket_added = false;
+                result.bucket_url = recommended.iter()
+                    .find(|bucket| bucket.name == result.bucket)
+                    .map(|bucket| bucket.url.clone());
+            }
+            result
+        })
+        .collect()
 }
 
 #[tauri::command]
-fn update_app(app_handle: tauri::AppHandle, app_name: String) -> Result<String, String> {
-    let app_handle_clone = app_handle.clone();
-    let app_name_clone = app_name.clone();
-    
-    // 发送开始更新事件
-    let _ = app_handle.emit("update-progress", install::InstallProgress {
-        app_name: app_name.clone(),
-        status: "starting".to_string(),
-        progress: 0.0,
-        message: format!("开始更新 {}...", app_name),
+fn update_app(
+    jobs: tauri::State<'_, jobs::JobManager>,
+    app_name: String,
+    scope: Option<paths::Scope>,
+) -> Result<jobs::JobInfo, AppError> {
+    let scope = scope.unwrap_or_default();
+    paths::check_elevation(scope)?;
+    
+    let command = scoop_command::ScoopCommand::update(&app_name, scope)?;
+    let job = jobs.submit(jobs::JobKind::Update, vec![app_name.clone()], scope, move |ctx| {
+        ctx.run_scoop(&command)?;
+        Ok(format!("{} 更新成功!", app_name))
     });
     
-    // 使用线程在后台执行更新
-    std::thread::spawn(move || {
-        // 模拟进度更新 (5秒内从0到100)
-        let total_steps = 50;
-        let step_duration = std::time::Duration::from_millis(100);
-        
-        for i in 1..=total_steps {
-            std::thread::sleep(step_duration);
-            let progress = i as f32 / total_steps as f32;
-            let message = if progress < 0.4 {
-                format!("正在检查 {} 的更新...", app_name_clone)
-            } else if progress < 0.7 {
-                format!("正在下载 {} 的新版本...", app_name_clone)
-            } else {
-                format!("正在安装 {} 的更新...", app_name_clone)
-            };
-            
-            let _ = app_handle_clone.emit("update-progress", install::InstallProgress {
-                app_name: app_name_clone.clone(),
-                status: "updating".to_string(),
-                progress,
-                message,
-            });
-        }
-        
-        let output = Command::new("powershell")
-            .args(["-NoProfile", "-Command", &format!("scoop update {}", app_name_clone)])
-            .creation_flags(0x08000000)
-            .output();
-        
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error_msg = String::from_utf8_lossy(&output.stderr).to_string();
-                    let _ = app_handle_clone.emit("update-progress", install::InstallProgress {
-                        app_name: app_name_clone.clone(),
-                        status: "error".to_string(),
-                        progress: 0.0,
-                        message: error_msg,
-                    });
-                } else {
-                    // 发送完成事件
-                    let _ = app_handle_clone.emit("update-progress", install::InstallProgress {
-                        app_name: app_name_clone.clone(),
-                        status: "completed".to_string(),
-                        progress: 1.0,
-                        message: format!("{} 更新成功!", app_name_clone),
-                    });
-                }
-            }
-            Err(e) => {
-                let _ = app_handle_clone.emit("update-progress", install::InstallProgress {
-                    app_name: app_name_clone.clone(),
-                    status: "error".to_string(),
-                    progress: 0.0,
-                    message: e.to_string(),
-                });
-            }
-        }
-    });
-    
-    Ok("更新已开始".to_string())
+    Ok(job)
 }
 
 #[tauri::command]
-fn update_all_apps(app_handle: tauri::AppHandle) -> Result<String, String> {
-    let app_handle_clone = app_handle.clone();
-    
-    // 发送开始批量更新事件
-    let _ = app_handle.emit("update-all-progress", install::InstallProgress {
-        app_name: "all".to_string(),
-        status: "starting".to_string(),
-        progress: 0.0,
-        message: "开始批量更新所有应用...".to_string(),
-    });
-    
-    // 使用线程在后台执行批量更新
-    std::thread::spawn(move || {
-        // 模拟进度更新 (5秒内从0到100)
-        let total_steps = 50;
-        let step_duration = std::time::Duration::from_millis(100);
-        
-        for i in 1..=total_steps {
-            std::thread::sleep(step_duration);
-            let progress = i as f32 / total_steps as f32;
-            let message = if progress < 0.3 {
-                "正在检查可更新的应用...".to_string()
-            } else if progress < 0.6 {
-                "正在下载更新...".to_string()
-            } else if progress < 0.9 {
-                "正在安装更新...".to_string()
-            } else {
-                "即将完成...".to_string()
-            };
-            
-            let _ = app_handle_clone.emit("update-all-progress", install::InstallProgress {
-                app_name: "all".to_string(),
-                status: "updating".to_string(),
-                progress,
-                message,
-            });
+fn update_all_apps(
+    jobs: tauri::State<'_, jobs::JobManager>,
+    index: tauri::State<'_, std::sync::Arc<manifest_index::ManifestIndex>>,
+) -> Result<jobs::JobInfo, AppError> {
+    let root = scoop_root()?;
+    let index = index.inner().clone();
+    let job = jobs.submit(jobs::JobKind::UpdateAll, vec!["*".to_string()], paths::Scope::User, move |ctx| {
+        // Like `scoop update *` this first updates Scoop and the buckets, then the current user's
+        // apps, but each outdated app is updated on its own so held apps are skipped explicitly
+        // and every app gets a result
+        // Scoop's own update prints no phases, so the progress starts with the first app
+        ctx.run_scoop(&scoop_command::ScoopCommand::update_scoop())?;
+        if ctx.is_cancelled() {
+            return Err(AppError::Cancelled);
+        }
+        let plan = updates::BulkUpdatePlan::new(&updates::app_statuses(&root, None, &index.entries(&root)));
+        for app in &plan.held {
+            ctx.record_app_result(app, jobs::AppOutcome::SkippedHeld, None);
         }
         
-        let output = Command::new("powershell")
-            .args(["-NoProfile", "-Command", "scoop update *"])
-            .creation_flags(0x08000000)
-            .output();
-        
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let error_msg = String::from_utf8_lossy(&output.stderr).to_string();
-                    let _ = app_handle_clone.emit("update-all-progress", install::InstallProgress {
-                        app_name: "all".to_string(),
-                        status: "error".to_string(),
-                        progress: 0.0,
-                        message: error_msg,
-                    });
-                } else {
-                    // 发送完成事件
-                    let _ = app_handle_clone.emit("update-all-progress", install::InstallProgress {
-                        app_name: "all".to_string(),
-                        status: "completed".to_string(),
-                        progress: 1.0,
-                        message: "所有应用更新完成!".to_string(),
-                    });
-                }
+        let mut failed = Vec::new();
+        for (step, app) in plan.update.iter().enumerate() {
+            if ctx.is_cancelled() {
+                break;
             }
-            Err(e) => {
-                let _ = app_handle_clone.emit("update-all-progress", install::InstallProgress {
-                    app_name: "all".to_string(),
-                    status: "error".to_string(),
-                    progress: 0.0,
-                    message: e.to_string(),
-                });
+            let command = scoop_command::ScoopCommand::update(app, paths::Scope::User)?;
+            // Each app fills its own share of the bar, starting where the previous one ended
+            let tracker = scoop_output::ProgressTracker::step(step, plan.update.len());
+            match ctx.run_scoop_step(&command, tracker) {
+                Ok(()) => ctx.record_app_result(app, jobs::AppOutcome::Updated, None),
+                Err(e) => {
+                    ctx.record_app_result(app, jobs::AppOutcome::Failed, Some(e.to_string()));
+                    failed.push(app.clone());
+                }
             }
         }
+        
+        if !failed.is_empty() {
+            return Err(AppError::UpdatesFailed(failed));
+        }
+        Ok(format!(
+            "所有应用更新完成!更新了 {} 个应用,跳过 {} 个已锁定的应用",
+            plan.update.len(),
+            plan.held.len()
+        ))
     });
     
-    Ok("批量更新已开始".to_string())
+    Ok(job)
 }
 
 #[tauri::command]
-fn update_scoop(app_handle: tauri::AppHandle) -> Result<String, String> {
-    // 发送开始更新事件
-    let _ = app_handle.emit("scoop-update-progress", install::InstallProgress {
-        app_name: "scoop".to_string(),
-        status: "starting".to_string(),
-        progress: 0.0,
-        message: "开始更新 Scoop 和 Buckets...".to_string(),
-    });
-    
-    // 使用线程在后台执行更新
-    std::thread::spawn(move || {
-        // 模拟进度更新
-        for i in 1..=3 {
-            std::thread::sleep(std::time::Duration::from_secs(1));
-            let progress = i as f32 / 10.0;
-            let _ = app_handle.emit("scoop-update-progress", install::InstallProgress {
-                app_name: "scoop".to_string(),
-                status: "updating".to_string(),
-                progress,
-                message: "正在更新 Scoop 和 Buckets...".to_string(),
-            });
-        }
-        
-        // 执行实际的 scoop update 命令
-        let output = Command::new("powershell")
-            .args(["-NoProfile", "-Command", "scoop update"])
-            .creation_flags(0x08000000)
-            .output();
-        
-        match output {
-            Ok(output) => {
-                if output.status.success() {
-                    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                    let _ = app_handle.emit("scoop-update-progress", install::InstallProgress {
-                        app_name: "scoop".to_string(),
-                        status: "completed".to_string(),
-                        progress: 1.0,
-                        message: format!("Scoop 和 Buckets 更新完成!\n{}", stdout),
-                    });
-                } else {
-                    let error_msg = String::from_utf8_lossy(&output.stderr).to_string();
-                    let _ = app_handle.emit("scoop-update-progress", install::InstallProgress {
-                        app_name: "scoop".to_string(),
-                        status: "error".to_string(),
-                        progress: 0.0,
-                        message: format!("更新失败: {}", error_msg),
-                    });
-                }
-            }
-            Err(e) => {
-                let _ = app_handle.emit("scoop-update-progress", install::InstallProgress {
-                    app_name: "scoop".to_string(),
-                    status: "error".to_string(),
-                    progress: 0.0,
-                    message: e.to_string(),
-                });
-            }
-        }
+async fn hold_app(
+    jobs: tauri::State<'_, jobs::JobManager>,
+    app_name: String,
+    scope: Option<paths::Scope>,
+) -> Result<String, AppError> {
+    set_hold(&jobs, app_name, scope.unwrap_or_default(), true).await
+}
+
+#[tauri::command]
+async fn unhold_app(
+    jobs: tauri::State<'_, jobs::JobManager>,
+    app_name: String,
+    scope: Option<paths::Scope>,
+) -> Result<String, AppError> {
+    set_hold(&jobs, app_name, scope.unwrap_or_default(), false).await
+}
+
+// `scoop hold` / `scoop unhold`, queued with the other mutations since both rewrite install.json
+async fn set_hold(jobs: &jobs::JobManager, app_name: String, scope: paths::Scope, hold: bool) -> Result<String, AppError> {
+    paths::check_elevation(scope)?;
+    let (kind, command) = if hold {
+        (jobs::JobKind::Hold, scoop_command::ScoopCommand::hold(&app_name, scope)?)
+    } else {
+        (jobs::JobKind::Unhold, scoop_command::ScoopCommand::unhold(&app_name, scope)?)
+    };
+    jobs.run(kind, vec![app_name], scope, move |ctx| {
+        ctx.run_scoop_quiet(&command)
+    })
+    .await
+}
+
+#[tauri::command]
+fn update_scoop(jobs: tauri::State<'_, jobs::JobManager>) -> Result<jobs::JobInfo, AppError> {
+    let job = jobs.submit(jobs::JobKind::UpdateScoop, vec!["scoop".to_string()], paths::Scope::User, move |ctx| {
+        ctx.run_scoop(&scoop_command::ScoopCommand::update_scoop())?;
+        Ok("Scoop 和 Buckets 更新完成!".to_string())
     });
     
-    Ok("Scoop 更新已开始".to_string())
+    Ok(job)
 }
 
 #[derive(Serialize, Deserialize)]
@@ -433,51 +411,7 @@ struct AppDetail {
     notes: Vec<String>,
     bin: Vec<String>,
     depends: Option<Vec<String>>,
-    suggest: Option<serde_json::Value>,
-}
-
-// Helper function to get list of apps with updates available
-fn get_updatable_apps() -> std::collections::HashSet<String> {
-    let mut updatable = std::collections::HashSet::new();
-    
-    let output = Command::new("powershell")
-        .args(["-NoProfile", "-Command", "scoop status"])
-        .creation_flags(0x08000000)
-        .output();
-    
-    if let Ok(output) = output {
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            // Parse scoop status output
-            // Format is a table with columns: Name, Installed Version, Available Version, Info
-            // Example: "redis            8.0.2             8.4.0101"
-            let mut in_table = false;
-            for line in stdout.lines() {
-                let line = line.trim();
-                
-                // Skip header and separator lines
-                if line.starts_with("Name") || line.starts_with("----") {
-                    in_table = true;
-                    continue;
-                }
-                
-                // Skip warnings and empty lines
-                if line.is_empty() || line.starts_with("WARN") || line.starts_with("Scoop") {
-                    continue;
-                }
-                
-                // If we're in the table and the line has content, extract the app name
-                if in_table && !line.is_empty() {
-                    // First word is the app name
-                    if let Some(name) = line.split_whitespace().next() {
-                        updatable.insert(name.to_string());
-                    }
-                }
-            }
-        }
-    }
-    
-    updatable
+    suggest: Option<std::collections::BTreeMap<String, Vec<String>>>,
 }
 
 // Helper function to calculate directory size
@@ -500,49 +434,63 @@ fn get_dir_size(path: &std::path::Path) -> u64 {
 }
 
 // Helper function to get app install size
-fn get_app_install_size(app_name: &str) -> u64 {
-    // Scoop apps are installed in ~/scoop/apps/<app_name>/current
-    if let Some(home) = std::env::var_os("USERPROFILE") {
-        let app_path = std::path::PathBuf::from(home)
-            .join("scoop")
-            .join("apps")
-            .join(app_name)
-            .join("current");
-        
-        if app_path.exists() {
-            return get_dir_size(&app_path);
-        }
+fn get_app_install_size(root: &Path, app_name: &str) -> u64 {
+    // Scoop apps are installed in <root>/apps/<app_name>/current
+    let app_path = root.join("apps").join(app_name).join("current");
+    if app_path.exists() {
+        return get_dir_size(&app_path);
     }
     
     0
 }
 
-// Helper function to parse .NET date format from scoop export
-fn parse_dotnet_date(date_str: &str) -> i64 {
-    // Format: "/Date(1744960672608)/"
-    if let Some(timestamp_str) = date_str.strip_prefix("/Date(").and_then(|s| s.strip_suffix(")/")) {
-        timestamp_str.parse::<i64>().unwrap_or(0)
-    } else {
-        0
-    }
+// When the app was installed or last updated, in Unix milliseconds. Like `scoop list`, this is
+// the modification time of install.json (rewritten on every install/update), falling back to
+// the version directory that `current` points to
+fn installed_at(current_path: &Path) -> i64 {
+    [current_path.join("install.json"), current_path.to_path_buf()]
+        .iter()
+        .find_map(|path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_millis() as i64)
 }
 
 
 #[tauri::command]
-fn get_installed_apps() -> Result<Vec<ScoopApp>, String> {
-    use std::path::PathBuf;
+fn get_installed_apps() -> Result<Vec<ScoopApp>, AppError> {
+    let user_root = scoop_root()?;
+    let mut apps = list_installed_apps(&user_root, paths::Scope::User);
+    
+    // Global apps live under a separate root (SCOOP_GLOBAL / ProgramData\scoop)
+    if let Ok(global_root) = paths::global_root() {
+        if global_root != user_root {
+            apps.extend(list_installed_apps(&global_root, paths::Scope::Global));
+        }
+    }
     
-    let apps_dir = std::env::var_os("USERPROFILE")
-        .map(|home| PathBuf::from(home).join("scoop").join("apps"))
-        .ok_or("Failed to get USERPROFILE")?;
+    Ok(apps)
+}
 
+// Lowercased app name -> installed version, user apps taking precedence over global ones
+fn installed_versions(user_root: &Path, global_root: Option<&Path>) -> std::collections::HashMap<String, String> {
+    let global_apps = global_root
+        .filter(|global_root| *global_root != user_root)
+        .map(|global_root| list_installed_apps(global_root, paths::Scope::Global))
+        .unwrap_or_default();
+    global_apps
+        .into_iter()
+        .chain(list_installed_apps(user_root, paths::Scope::User))
+        .map(|app| (app.name.to_lowercase(), app.version))
+        .collect()
+}
+
+fn list_installed_apps(root: &Path, scope: paths::Scope) -> Vec<ScoopApp> {
+    let apps_dir = root.join("apps");
     if !apps_dir.exists() {
-        return Ok(Vec::new());
+        return Vec::new();
     }
 
-    // Optimization: Skip synchronous update check to avoid UI freeze
-    // Updates will be checked asynchronously via check_updates_async command
-    // let updatable_apps = get_updatable_apps(); 
+    // Updates are checked asynchronously via check_updates_async command
     
     let mut result = Vec::new();
     
@@ -556,50 +504,45 @@ fn get_installed_apps() -> Result<Vec<ScoopApp>, String> {
                 if !current_path.exists() { continue; }
                 
                 // Read manifest.json for version/desc
-                let mut version = String::new();
-                let mut description = String::new();
-                
-                let manifest_path = current_path.join("manifest.json");
-                if let Ok(content) = std::fs::read_to_string(&manifest_path) {
-                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                        version = json.get("version").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                        description = json.get("description").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                    }
-                }
+                let manifest = manifest::Manifest::read(&current_path.join("manifest.json")).unwrap_or_default();
                 
-                // Read install.json for bucket
-                let mut bucket = "unknown".to_string();
-                let install_path = current_path.join("install.json");
-                if let Ok(content) = std::fs::read_to_string(&install_path) {
-                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                        bucket = json.get("bucket").and_then(|v| v.as_str()).unwrap_or("unknown").to_string();
-                    }
-                }
+                // Read install.json for bucket, architecture, source URL and hold
+                let install_info = manifest::InstallInfo::read(&current_path).unwrap_or_default();
                 
                 // Check failed install? Usually if 'current' exists, it's fine.
                 
                  result.push(ScoopApp {
                     name: name.clone(),
-                    version,
-                    bucket,
-                    description,
-                    updated: 0, 
+                    version: manifest.version,
+                    bucket: install_info.bucket.unwrap_or_else(|| "unknown".to_string()),
+                    description: manifest.description,
+                    updated: installed_at(&current_path),
                     has_update: false,  // Will be updated asynchronously
                     install_size: 0,
+                    scope,
+                    architecture: install_info.architecture,
+                    url: install_info.url,
+                    held: install_info.hold,
                 });
             }
         }
     }
 
-    Ok(result)
+    result
 }
 
 #[tauri::command]
-fn get_app_sizes(app_names: Vec<String>) -> Result<std::collections::HashMap<String, u64>, String> {
+fn get_app_sizes(app_names: Vec<String>) -> Result<std::collections::HashMap<String, u64>, AppError> {
+    let root = scoop_root()?;
+    let global_root = paths::global_root().ok();
     let mut sizes = std::collections::HashMap::new();
     
     for app_name in app_names {
-        let size = get_app_install_size(&app_name);
+        // Fall back to the global root for apps installed with -g
+        let size = match get_app_install_size(&root, &app_name) {
+            0 => global_root.as_deref().map_or(0, |global| get_app_install_size(global, &app_name)),
+            size => size,
+        };
         sizes.insert(app_name, size);
     }
     
@@ -607,19 +550,31 @@ fn get_app_sizes(app_names: Vec<String>) -> Result<std::collections::HashMap<Str
 }
 
 #[tauri::command]
-fn check_updates_async(app_handle: tauri::AppHandle) -> Result<String, String> {
-    // Spawn a background thread to check for updates asynchronously
-    std::thread::spawn(move || {
-        // Get list of updatable apps (this is slow, 2-5 seconds)
-        let updatable_apps = get_updatable_apps();
-        
-        // Convert HashSet to Vec for serialization
-        let updatable_list: Vec<String> = updatable_apps.into_iter().collect();
+fn check_updates_async(
+    app_handle: tauri::AppHandle,
+    jobs: tauri::State<'_, jobs::JobManager>,
+    index: tauri::State<'_, std::sync::Arc<manifest_index::ManifestIndex>>,
+) -> Result<String, AppError> {
+    let root = scoop_root()?;
+    let index = index.inner().clone();
+    // Read-only job: runs in parallel with the mutation queue
+    jobs.submit(jobs::JobKind::CheckUpdates, vec!["*".to_string()], paths::Scope::User, move |_| {
+        // Compared offline against the local buckets, no `scoop status` round trip
+        let global_root = paths::global_root().ok();
+        let mut statuses = updates::app_statuses(&root, global_root.as_deref(), &index.entries(&root));
+        statuses.retain(updates::AppStatus::needs_attention);
+        let updatable_list: Vec<String> = statuses.iter()
+            .filter(|status| status.is_updatable())
+            .map(|status| status.name.clone())
+            .collect();
         
         // Emit event to frontend with the list of updatable apps
+        let count = updatable_list.len();
         let _ = app_handle.emit("updates-checked", UpdatesCheckedPayload {
             updatable_apps: updatable_list,
+            apps: statuses,
         });
+        Ok(format!("{} updatable apps", count))
     });
     
     Ok("Update check started in background".to_string())
@@ -628,9 +583,11 @@ fn check_updates_async(app_handle: tauri::AppHandle) -> Result<String, String> {
 
 
 #[tauri::command]
-fn get_random_apps(count: usize) -> Result<Vec<DiscoverApp>, String> {
+fn get_random_apps(
+    index: tauri::State<'_, std::sync::Arc<manifest_index::ManifestIndex>>,
+    count: usize,
+) -> Result<Vec<DiscoverApp>, AppError> {
     use rand::seq::SliceRandom;
-    use std::path::PathBuf;
     
     // Material icon pool for random selection
     let icons = vec![
@@ -643,280 +600,141 @@ fn get_random_apps(count: usize) -> Result<Vec<DiscoverApp>, String> {
         "wifi", "work", "apps", "archive", "bookmark",
     ];
     
-    // Get scoop buckets directory
-    let buckets_dir = std::env::var_os("USERPROFILE")
-        .map(|home| PathBuf::from(home).join("scoop").join("buckets"))
-        .ok_or("Failed to get USERPROFILE")?;
+    // All manifests from all buckets, from the index
+    let entries = index.entries(&scoop_root()?);
     
-    if !buckets_dir.exists() {
-        return Err("Scoop buckets directory not found".to_string());
-    }
-    
-    // Collect all manifest files from all buckets
-    let mut manifest_files = Vec::new();
-    
-    if let Ok(bucket_entries) = std::fs::read_dir(&buckets_dir) {
-        for bucket_entry in bucket_entries.flatten() {
-            if bucket_entry.path().is_dir() {
-                let bucket_name = bucket_entry.file_name().to_string_lossy().to_string();
-                let bucket_dir = bucket_entry.path().join("bucket");
-                
-                if bucket_dir.exists() {
-                    if let Ok(manifest_entries) = std::fs::read_dir(&bucket_dir) {
-                        for manifest_entry in manifest_entries.flatten() {
-                            let path = manifest_entry.path();
-                            if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                                manifest_files.push((path, bucket_name.clone()));
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    if manifest_files.is_empty() {
-        return Err("No manifest files found".to_string());
+    if entries.is_empty() {
+        return Err(AppError::NoBuckets);
     }
     
     // Randomly select manifests
     let mut rng = rand::thread_rng();
-    manifest_files.shuffle(&mut rng);
-    
-    let selected_count = count.min(manifest_files.len());
-    let mut apps = Vec::new();
-    
-    for (manifest_path, bucket_name) in manifest_files.iter().take(selected_count) {
-        // Parse manifest JSON
-        if let Ok(content) = std::fs::read_to_string(manifest_path) {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                let name = manifest_path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                
-                let description = json.get("description")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                
-                let version = json.get("version")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                
-                let homepage = json.get("homepage")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                
-                // Randomly select an icon
-                let icon = icons.choose(&mut rng)
-                    .unwrap_or(&"apps")
-                    .to_string();
-                
-                apps.push(DiscoverApp {
-                    name,
-                    description,
-                    version,
-                    homepage,
-                    bucket: bucket_name.clone(),
-                    icon,
-                });
-            }
-        }
-    }
+    let apps = entries
+        .choose_multiple(&mut rng, count)
+        .map(|entry| DiscoverApp {
+            name: entry.name.clone(),
+            description: entry.description.clone(),
+            version: entry.version.clone(),
+            homepage: entry.homepage.clone(),
+            bucket: entry.bucket.clone(),
+            // Randomly select an icon
+            icon: icons.choose(&mut rng).unwrap_or(&"apps").to_string(),
+        })
+        .collect();
     
     Ok(apps)
 }
 
 #[tauri::command]
-fn get_app_detail(app_name: String, bucket: String) -> Result<AppDetail, String> {
-    use std::path::PathBuf;
-    
+fn get_app_detail(app_name: String, bucket: String) -> Result<AppDetail, AppError> {
+    read_app_detail(&scoop_root()?, app_name, bucket)
+}
+
+fn read_app_detail(root: &Path, app_name: String, bucket: String) -> Result<AppDetail, AppError> {
+    // Both names come from the frontend, validate them before building the manifest path
+    scoop_command::validate_app(&app_name)?;
+    scoop_command::validate_bucket(&bucket)?;
     // Construct path to manifest file
-    let manifest_path = std::env::var_os("USERPROFILE")
-        .map(|home| {
-            PathBuf::from(home)
-                .join("scoop")
-                .join("buckets")
-                .join(&bucket)
-                .join("bucket")
-                .join(format!("{}.json", app_name))
-        })
-        .ok_or("Failed to get USERPROFILE")?;
+    let manifest_path = manifest::bucket_manifest_path(root, &bucket, &app_name);
     
     if !manifest_path.exists() {
-        return Err(format!("Manifest not found for {} in bucket {}", app_name, bucket));
+        return Err(AppError::ManifestNotFound { app: app_name, bucket: Some(bucket) });
     }
     
-    // Read and parse manifest
-    let content = std::fs::read_to_string(&manifest_path)
-        .map_err(|e| format!("Failed to read manifest: {}", e))?;
-    
-    let json: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse manifest: {}", e))?;
-    
-    // Extract fields
-    let version = json.get("version")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
-    
-    let description = json.get("description")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
-    
-    let homepage = json.get("homepage")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
-    
-    let license = json.get("license")
-        .and_then(|v| v.as_str())
-        .or_else(|| {
-            json.get("license")
-                .and_then(|v| v.get("identifier"))
-                .and_then(|v| v.as_str())
-        })
-        .unwrap_or("Unknown")
-        .to_string();
-    
-    // Extract notes (can be string or array)
-    let notes = match json.get("notes") {
-        Some(serde_json::Value::String(s)) => vec![s.clone()],
-        Some(serde_json::Value::Array(arr)) => {
-            arr.iter()
-                .filter_map(|v| v.as_str())
-                .map(|s| s.to_string())
-                .collect()
-        }
-        _ => vec![],
-    };
-    
-    // Extract bin (can be string or array)
-    let bin = match json.get("bin") {
-        Some(serde_json::Value::String(s)) => vec![s.clone()],
-        Some(serde_json::Value::Array(arr)) => {
-            arr.iter()
-                .filter_map(|v| {
-                    if let Some(s) = v.as_str() {
-                        Some(s.to_string())
-                    } else if let Some(arr) = v.as_array() {
-                        arr.first().and_then(|v| v.as_str()).map(|s| s.to_string())
-                    } else {
-                        None
-                    }
-                })
-                .collect()
-        }
-        _ => vec![],
-    };
-    
-    // Extract depends
-    let depends = json.get("depends").and_then(|v| match v {
-        serde_json::Value::String(s) => Some(vec![s.clone()]),
-        serde_json::Value::Array(arr) => Some(
-            arr.iter()
-                .filter_map(|v| v.as_str())
-                .map(|s| s.to_string())
-                .collect()
-        ),
-        _ => None,
-    });
-    
-    // Extract suggest (keep as JSON value for flexibility)
-    let suggest = json.get("suggest").cloned();
+    let manifest = manifest::Manifest::read(&manifest_path)?;
+    let license = manifest.license_identifier().unwrap_or("Unknown").to_string();
+    let bin = manifest.bins().into_iter().map(|bin| bin.path.clone()).collect();
     
     Ok(AppDetail {
         name: app_name,
-        version,
-        description,
-        homepage,
+        version: manifest.version,
+        description: manifest.description,
+        homepage: manifest.homepage,
         license,
         bucket,
-        notes,
+        notes: manifest.notes,
         bin,
-        depends,
-        suggest,
+        depends: Some(manifest.depends).filter(|depends| !depends.is_empty()),
+        suggest: Some(manifest.suggest).filter(|suggest| !suggest.is_empty()),
     })
 }
 
 #[tauri::command]
-fn search_local_packets(query: String) -> Result<Vec<SearchResult>, String> {
-    use std::path::PathBuf;
-    
-    // Normalize query
-    let query_lower = query.to_lowercase();
-    let mut results = Vec::new();
-    
-    // Get scoop buckets directory
-    let buckets_dir = std::env::var_os("USERPROFILE")
-        .map(|home| PathBuf::from(home).join("scoop").join("buckets"))
-        .ok_or("Failed to get USERPROFILE")?;
-    
-    if !buckets_dir.exists() {
-        return Ok(Vec::new());
-    }
-    
-    // Walk through buckets
-    if let Ok(bucket_entries) = std::fs::read_dir(&buckets_dir) {
-        for bucket_entry in bucket_entries.flatten() {
-            if bucket_entry.path().is_dir() {
-                let bucket_name = bucket_entry.file_name().to_string_lossy().to_string();
-                let bucket_dir = bucket_entry.path().join("bucket");
-                
-                if bucket_dir.exists() {
-                    if let Ok(manifest_entries) = std::fs::read_dir(&bucket_dir) {
-                        for manifest_entry in manifest_entries.flatten() {
-                            // Check if filename matches query
-                            let file_name = manifest_entry.file_name().to_string_lossy().to_string();
-                            if let Some(stem) = file_name.strip_suffix(".json") {
-                                if stem.to_lowercase().contains(&query_lower) {
-                                    // Found a match! Parse basic info.
-                                    let mut version = String::new();
-                                    let mut description = String::new();
-                                    
-                                    // Optimistic: try to read file for details
-                                    if let Ok(content) = std::fs::read_to_string(manifest_entry.path()) {
-                                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                                            version = json.get("version").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                                            description = json.get("description").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                                        }
-                                    }
-                                    
-                                    results.push(SearchResult {
-                                        name: stem.to_string(),
-                                        version,
-                                        bucket: bucket_name.clone(),
-                                        description,
-                                    });
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+async fn search_local_packets(
+    index: tauri::State<'_, std::sync::Arc<manifest_index::ManifestIndex>>,
+    generations: tauri::State<'_, std::sync::Arc<search::Generations>>,
+    request: SearchRequest,
+    request_id: Option<u64>,
+) -> Result<LocalSearchResponse, AppError> {
+    let index = index.inner().clone();
+    run_search(&generations, search::SearchChannel::Local, request_id, move |superseded| {
+        let root = scoop_root()?;
+        let entries = index.entries(&root);
+        if superseded() {
+            return Err(AppError::Cancelled);
         }
+        let installed = installed_versions(&root, paths::global_root().ok().as_deref());
+        Ok(search_local(&entries, &installed, &request))
+    })
+    .await
+}
+
+fn search_local(
+    entries: &[manifest_index::IndexEntry],
+    installed: &std::collections::HashMap<String, String>,
+    request: &SearchRequest,
+) -> LocalSearchResponse {
+    let mut options = search::SearchOptions {
+        offset: request.offset,
+        ..Default::default()
+    };
+    if let Some(limit) = request.limit {
+        options.limit = limit.min(MAX_SEARCH_PAGE);
+    }
+    if let Some(max_typos) = request.max_typos {
+        options.max_typos = max_typos;
     }
     
-    // Sort results by length of name match (exact matches first)
-    results.sort_by(|a, b| {
-        let a_starts = a.name.to_lowercase().starts_with(&query_lower);
-        let b_starts = b.name.to_lowercase().starts_with(&query_lower);
-        if a_starts && !b_starts { return std::cmp::Ordering::Less; }
-        if !a_starts && b_starts { return std::cmp::Ordering::Greater; }
-        a.name.len().cmp(&b.name.len())
-    });
+    // Facets narrow the candidates first, then the rest is ranked by relevance
+    let candidates = entries.iter().filter(|entry| request.facets.matches(entry, installed));
+    let outcome = search::search(candidates, &request.query, &options);
+    let results = outcome.hits
+        .into_iter()
+        .map(|hit| search_result(hit, installed))
+        .collect();
+    
+    LocalSearchResponse {
+        results,
+        total: outcome.total,
+        offset: request.offset,
+        suggestions: outcome.suggestions,
+    }
+}
 
-    // Cap results to avoid UI lag
-    if results.len() > 50 {
-        results.truncate(50);
+fn search_result(hit: search::SearchHit, installed: &std::collections::HashMap<String, String>) -> SearchResult {
+    let installed_version = search::installed_version(hit.entry, installed);
+    SearchResult {
+        name: hit.entry.name.clone(),
+        version: hit.entry.version.clone(),
+        bucket: hit.entry.bucket.clone(),
+        description: hit.entry.description.clone(),
+        homepage: hit.entry.homepage.clone(),
+        bins: hit.entry.bins.clone(),
+        score: hit.score,
+        highlights: hit.highlights,
+        installed: installed_version.is_some(),
+        has_update: search::has_update(hit.entry, installed_version),
+        bucket_added: true,
+        bucket_url: None,
+    }
+}
+
+// A failed index save only costs a full rebuild on the next start, so it is reported to the frontend
+// instead of failing whichever command triggered the refresh
+fn report_index_error(app_handle: tauri::AppHandle) -> impl Fn(AppError) + Send + Sync + 'static {
+    move |error| {
+        let _ = app_handle.emit(manifest_index::INDEX_ERROR_EVENT, error);
     }
-    
-    Ok(results)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -924,12 +742,39 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            // Operation history lives under the app data dir so it survives restarts
+            let app_data_dir = app.path().app_data_dir()?;
+            paths::init(&app_data_dir);
+            let history = std::sync::Arc::new(history::History::open(app_data_dir.join("history")));
+            app.manage(jobs::JobManager::new(app.handle().clone(), history.clone()));
+            app.manage(history);
+
+            // The manifest index is saved between runs; bring it up to date in the background
+            let index = std::sync::Arc::new(manifest_index::ManifestIndex::open(app_data_dir.join("manifest-index.json")));
+            index.on_error(report_index_error(app.handle().clone()));
+            app.manage(index.clone());
+            // Recommended buckets that are not added are cloned into the catalogue on request (sync_catalogue)
+            // so search can find their apps
+            let catalogue = catalogue::Catalogue::open(app_data_dir.join("bucket-catalogue"));
+            catalogue.on_index_error(report_index_error(app.handle().clone()));
+            app.manage(std::sync::Arc::new(catalogue));
+            app.manage(std::sync::Arc::new(search::Generations::default()));
+            std::thread::spawn(move || {
+                if let Ok(root) = paths::scoop_root() {
+                    index.entries(&root);
+                }
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_installed_apps, 
             search_apps, 
             search_local_packets,
             update_app, 
             update_all_apps,
+            hold_app,
+            unhold_app,
             update_scoop,
             get_app_sizes,
             check_updates_async,
@@ -941,8 +786,167 @@ pub fn run() {
             install::install_app,
             install::uninstall_app,
             install::check_dependencies,
-            install::is_app_installed
+            install::is_app_installed,
+            manifest_index::find_by_binary,
+            catalogue::sync_catalogue,
+            jobs::list_jobs,
+            jobs::get_job,
+            jobs::cancel_operation,
+            history::get_operation_history,
+            history::get_operation_log,
+            paths::get_scoop_root,
+            paths::set_scoop_root,
+            paths::get_global_root,
+            paths::is_elevated_process
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A miniature scoop root checked in under tests/fixtures/scoop
+    pub(crate) fn fixture_root() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/scoop")
+    }
+
+    #[test]
+    fn lists_buckets_in_root() {
+        let mut names: Vec<String> = list_buckets(&fixture_root()).into_iter().map(|b| b.name).collect();
+        names.sort();
+        assert_eq!(names, ["extras", "main"]);
+        assert!(list_buckets(&fixture_root().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn lists_installed_apps_with_current_dir() {
+        let mut apps = list_installed_apps(&fixture_root(), paths::Scope::User);
+        apps.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // scoop itself and apps without a `current` dir are skipped
+        let names: Vec<&str> = apps.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["7zip", "git", "nodejs"]);
+        assert_eq!(apps[0].version, "23.01");
+        assert_eq!(apps[0].bucket, "main");
+        assert_eq!(apps[2].version, "20.11.0");
+        assert!(apps.iter().all(|a| a.scope == paths::Scope::User));
+
+        // Metadata from install.json
+        assert_eq!(apps[0].architecture.as_deref(), Some("64bit"));
+        assert!(apps[1].held && !apps[0].held);
+        assert!(apps[0].url.is_none());
+        let install_json = fixture_root().join("apps/7zip/current/install.json");
+        let modified = std::fs::metadata(install_json).unwrap().modified().unwrap();
+        let expected = modified.duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64;
+        assert_eq!(apps[0].updated, expected);
+    }
+
+    #[test]
+    fn measures_install_size() {
+        let root = fixture_root();
+        let expected: u64 = std::fs::read_dir(root.join("apps/7zip/current"))
+            .unwrap()
+            .map(|e| e.unwrap().metadata().unwrap().len())
+            .sum();
+        assert_eq!(get_app_install_size(&root, "7zip"), expected);
+        assert_eq!(get_app_install_size(&root, "not-installed"), 0);
+    }
+
+    fn query(query: &str) -> SearchRequest {
+        SearchRequest { query: query.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn searches_manifest_names() {
+        let entries = manifest_index::ManifestIndex::in_memory().entries(&fixture_root());
+        let installed = installed_versions(&fixture_root(), None);
+        let results = search_local(&entries, &installed, &query("Node")).results;
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["nodejs", "nodejs-lts"]);
+        assert_eq!(results[0].version, "21.6.1");
+        assert_eq!(results[0].bucket, "main");
+        assert!(search_local(&entries, &installed, &query("no-such-app")).results.is_empty());
+        // Descriptions are searched too
+        assert_eq!(search_local(&entries, &installed, &query("editor")).results[0].name, "vscode");
+        // Typos fall back to fuzzy name matches with suggestions
+        assert_eq!(search_local(&entries, &installed, &query("vscdoe")).suggestions, ["vscode"]);
+    }
+
+    #[test]
+    fn filters_and_pages_local_search() {
+        let entries = manifest_index::ManifestIndex::in_memory().entries(&fixture_root());
+        let installed = installed_versions(&fixture_root(), None);
+        let names = |response: &LocalSearchResponse| -> Vec<String> {
+            response.results.iter().map(|r| r.name.clone()).collect()
+        };
+
+        // Browse a whole bucket page by page
+        let mut request = SearchRequest { limit: Some(2), ..Default::default() };
+        request.facets.buckets = vec!["main".to_string()];
+        let page = search_local(&entries, &installed, &request);
+        assert_eq!((page.total, names(&page)), (4, vec!["7zip".to_string(), "git".to_string()]));
+        request.offset = 2;
+        let page = search_local(&entries, &installed, &request);
+        assert_eq!(names(&page), ["nodejs", "nodejs-lts"]);
+        assert!(page.results[0].installed && page.results[0].has_update);
+        assert!(!page.results[1].installed);
+
+        let mut request = query("node");
+        request.facets.has_update = Some(true);
+        assert_eq!(names(&search_local(&entries, &installed, &request)), ["nodejs"]);
+        request.facets = search::Facets { installed: Some(false), ..Default::default() };
+        assert_eq!(names(&search_local(&entries, &installed, &request)), ["nodejs-lts"]);
+        request.facets = search::Facets { architecture: Some("arm64".to_string()), ..Default::default() };
+        assert_eq!(names(&search_local(&entries, &installed, &request)), ["nodejs-lts"]);
+
+        let mut request = query("");
+        request.facets.licenses = vec!["MIT".to_string()];
+        assert_eq!(search_local(&entries, &installed, &request).total, 2);
+    }
+
+    #[test]
+    fn searches_buckets_that_are_not_added() {
+        let entries = manifest_index::ManifestIndex::in_memory().entries(&fixture_root());
+        let installed = installed_versions(&fixture_root(), None);
+        let catalogue = catalogue::Catalogue::in_memory(fixture_root());
+
+        // While extras is added the catalogue adds nothing
+        let results = search_all_buckets(&entries, &catalogue, &installed, "code", true);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].bucket_added && results[0].bucket_url.is_none());
+
+        // After removing extras, vscode is still found but flagged as not added
+        let main_only: Vec<_> = entries.iter().filter(|entry| entry.bucket == "main").cloned().collect();
+        let results = search_all_buckets(&main_only, &catalogue, &installed, "code", true);
+        assert_eq!((results[0].name.as_str(), results[0].bucket.as_str()), ("vscode", "extras"));
+        assert!(!results[0].bucket_added);
+        assert_eq!(results[0].bucket_url.as_deref(), Some("https://github.com/ScoopInstaller/Extras"));
+        assert!(search_all_buckets(&main_only, &catalogue, &installed, "editor", false).is_empty());
+
+        let results = search_all_buckets(&main_only, &catalogue, &installed, "7zip", true);
+        assert!(results[0].bucket_added && results[0].installed && !results[0].has_update);
+    }
+
+    #[test]
+    fn reads_app_detail() {
+        let detail = read_app_detail(&fixture_root(), "vscode".to_string(), "extras".to_string()).unwrap();
+        assert_eq!(detail.version, "1.85.2");
+        assert_eq!(detail.license, "Freeware");
+        assert_eq!(detail.bin, ["bin\\code.cmd"]);
+        assert_eq!(detail.depends, Some(vec!["7zip".to_string()]));
+        assert_eq!(detail.notes.len(), 2);
+
+        let git = read_app_detail(&fixture_root(), "git".to_string(), "main".to_string()).unwrap();
+        assert_eq!(git.license, "GPL-2.0-only");
+        assert_eq!(git.bin, ["cmd\\git.exe", "usr\\bin\\bash.exe"]);
+        assert!(git.suggest.is_some());
+
+        assert!(read_app_detail(&fixture_root(), "vscode".to_string(), "main".to_string()).is_err());
+        for (app, bucket) in [("vscode", "../../.."), ("..\\vscode", "extras")] {
+            let error = read_app_detail(&fixture_root(), app.to_string(), bucket.to_string()).err();
+            assert_eq!(error.as_ref().map(AppError::code), Some("invalid_argument"));
+        }
+    }
+}
@@ -0,0 +1,524 @@
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+
+/// scoop 应用 manifest(bucket 中的 `<app>.json`,以及安装后 `current\manifest.json`)
+///
+/// 字段对应 scoop 的 manifest schema。很多字段既可以写成单个值也可以写成数组,
+/// 这里统一解析为 `Vec`;未用到的字段(`##` 注释等)会被忽略。
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    pub version: String,
+    pub description: String,
+    pub homepage: String,
+    pub license: Option<License>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub notes: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub depends: Vec<String>,
+    /// 功能名 -> 可以提供该功能的应用,例如 `"vimtutor": "vim"`
+    #[serde(deserialize_with = "suggest_map")]
+    pub suggest: BTreeMap<String, Vec<String>>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub url: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub hash: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub extract_dir: Vec<String>,
+    pub architecture: Option<Architectures<ArchSpec>>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub bin: Vec<Bin>,
+    pub shortcuts: Vec<Shortcut>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub persist: Vec<Persist>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub env_add_path: Vec<String>,
+    pub env_set: BTreeMap<String, String>,
+    pub installer: Option<Installer>,
+    pub uninstaller: Option<Installer>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub pre_install: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub post_install: Vec<String>,
+    pub checkver: Option<Checkver>,
+    pub autoupdate: Option<Autoupdate>,
+}
+
+/// `license` 可以是 SPDX 标识符,也可以是 `{ "identifier": ..., "url": ... }`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum License {
+    Identifier(String),
+    Detailed {
+        identifier: String,
+        #[serde(default)]
+        url: Option<String>,
+    },
+}
+
+impl License {
+    pub fn identifier(&self) -> &str {
+        match self {
+            License::Identifier(identifier) | License::Detailed { identifier, .. } => identifier,
+        }
+    }
+}
+
+/// 按架构区分的字段,键为 `64bit`、`32bit`、`arm64`
+#[derive(Clone, Debug, Deserialize)]
+pub struct Architectures<T> {
+    #[serde(rename = "64bit")]
+    pub x64: Option<T>,
+    #[serde(rename = "32bit")]
+    pub x86: Option<T>,
+    pub arm64: Option<T>,
+}
+
+impl<T> Architectures<T> {
+    /// 按 scoop 的架构名(`64bit`/`32bit`/`arm64`)取对应的块
+    pub fn get(&self, arch: &str) -> Option<&T> {
+        match arch {
+            "64bit" => self.x64.as_ref(),
+            "32bit" => self.x86.as_ref(),
+            "arm64" => self.arm64.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// manifest 中出现的架构名,按 64bit、32bit、arm64 的顺序
+    pub fn names(&self) -> Vec<&'static str> {
+        [("64bit", self.x64.is_some()), ("32bit", self.x86.is_some()), ("arm64", self.arm64.is_some())]
+            .into_iter()
+            .filter(|(_, present)| *present)
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+/// `architecture` 块中可以覆盖顶层的字段
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ArchSpec {
+    #[serde(deserialize_with = "one_or_many")]
+    pub url: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub hash: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub extract_dir: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub bin: Vec<Bin>,
+    pub shortcuts: Vec<Shortcut>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub env_add_path: Vec<String>,
+    pub env_set: BTreeMap<String, String>,
+    pub installer: Option<Installer>,
+    pub uninstaller: Option<Installer>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub pre_install: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub post_install: Vec<String>,
+}
+
+/// `bin` 中的一项:`"bin\\tool.exe"` 或 `["bin\\tool.exe", "alias", "--args"]`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "PathOrList")]
+pub struct Bin {
+    pub path: String,
+    pub alias: Option<String>,
+    pub args: Option<String>,
+}
+
+impl Bin {
+    /// 创建的 shim 名:别名,没有别名时为去掉扩展名的文件名
+    pub fn shim_name(&self) -> String {
+        if let Some(alias) = &self.alias {
+            return alias.clone();
+        }
+        let file = self.path.rsplit(['\\', '/']).next().unwrap_or(&self.path);
+        match file.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+            _ => file.to_string(),
+        }
+    }
+}
+
+impl TryFrom<PathOrList> for Bin {
+    type Error = String;
+
+    fn try_from(value: PathOrList) -> Result<Self, Self::Error> {
+        let mut parts = value.into_parts()?.into_iter();
+        Ok(Bin {
+            path: parts.next().unwrap_or_default(),
+            alias: parts.next(),
+            args: parts.next(),
+        })
+    }
+}
+
+/// `shortcuts` 中的一项:`[目标, 快捷方式名, 参数, 图标]`,后两项可省略
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct Shortcut {
+    pub target: String,
+    pub name: String,
+    pub args: Option<String>,
+    pub icon: Option<String>,
+}
+
+impl TryFrom<Vec<String>> for Shortcut {
+    type Error = String;
+
+    fn try_from(value: Vec<String>) -> Result<Self, Self::Error> {
+        let mut parts = value.into_iter();
+        match (parts.next(), parts.next()) {
+            (Some(target), Some(name)) => Ok(Shortcut {
+                target,
+                name,
+                args: parts.next(),
+                icon: parts.next(),
+            }),
+            _ => Err("a shortcut needs a target and a name".to_string()),
+        }
+    }
+}
+
+/// `persist` 中的一项:`"data"` 或 `["应用内路径", "persist 目录中的名字"]`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "PathOrList")]
+pub struct Persist {
+    pub source: String,
+    pub target: Option<String>,
+}
+
+impl TryFrom<PathOrList> for Persist {
+    type Error = String;
+
+    fn try_from(value: PathOrList) -> Result<Self, Self::Error> {
+        let mut parts = value.into_parts()?.into_iter();
+        Ok(Persist {
+            source: parts.next().unwrap_or_default(),
+            target: parts.next(),
+        })
+    }
+}
+
+/// `installer` / `uninstaller`:运行一个文件,或者执行一段脚本
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Installer {
+    pub file: Option<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub args: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub script: Vec<String>,
+    pub keep: bool,
+}
+
+/// `checkver`:`"github"`、一个正则,或者描述如何取得最新版本的对象
+///
+/// 只为完整解析 manifest,目前还没有读取其中的内容。
+#[allow(dead_code)]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Checkver {
+    Regex(String),
+    Detailed(CheckverSpec),
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CheckverSpec {
+    pub url: Option<String>,
+    #[serde(alias = "re")]
+    pub regex: Option<String>,
+    #[serde(alias = "jp")]
+    pub jsonpath: Option<String>,
+    pub xpath: Option<String>,
+    pub github: Option<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub script: Vec<String>,
+    pub replace: Option<String>,
+    pub reverse: bool,
+}
+
+/// `autoupdate`:新版本的下载地址模板及哈希的获取方式
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Autoupdate {
+    #[serde(deserialize_with = "one_or_many")]
+    pub url: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub hash: Vec<HashExtraction>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub extract_dir: Vec<String>,
+    pub architecture: Option<Architectures<AutoupdateArch>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AutoupdateArch {
+    #[serde(deserialize_with = "one_or_many")]
+    pub url: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub hash: Vec<HashExtraction>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub extract_dir: Vec<String>,
+}
+
+/// autoupdate 中获取新版本哈希的方式
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HashExtraction {
+    pub url: Option<String>,
+    #[serde(alias = "find")]
+    pub regex: Option<String>,
+    #[serde(alias = "jp")]
+    pub jsonpath: Option<String>,
+    pub xpath: Option<String>,
+    pub mode: Option<String>,
+}
+
+impl Manifest {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        // 用 PowerShell 写出的 manifest 可能带 BOM
+        serde_json::from_str(content.trim_start_matches('\u{feff}'))
+            .map_err(|e| format!("Failed to parse manifest: {}", e))
+    }
+
+    pub fn read(path: &Path) -> Result<Self, AppError> {
+        let content = std::fs::read_to_string(path).map_err(|e| AppError::io(path, e))?;
+        Self::parse(&content).map_err(|reason| AppError::ManifestInvalid {
+            path: path.to_path_buf(),
+            reason,
+        })
+    }
+
+    pub fn license_identifier(&self) -> Option<&str> {
+        self.license.as_ref().map(License::identifier)
+    }
+
+    /// 应用提供的命令:顶层的 `bin`,加上各架构块中的 `bin`(去重)
+    pub fn bins(&self) -> Vec<&Bin> {
+        self.with_architectures(&self.bin, |spec| &spec.bin)
+    }
+
+    /// 应用创建的开始菜单快捷方式:顶层的 `shortcuts`,加上各架构块中的(去重)
+    pub fn all_shortcuts(&self) -> Vec<&Shortcut> {
+        self.with_architectures(&self.shortcuts, |spec| &spec.shortcuts)
+    }
+
+    fn with_architectures<'a, T: PartialEq>(&'a self, top: &'a [T], field: impl Fn(&'a ArchSpec) -> &'a [T]) -> Vec<&'a T> {
+        let mut items: Vec<&T> = top.iter().collect();
+        if let Some(architecture) = &self.architecture {
+            for spec in [&architecture.x64, &architecture.x86, &architecture.arm64].into_iter().flatten() {
+                for item in field(spec) {
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            }
+        }
+        items
+    }
+}
+
+/// 安装后 `current\install.json` 中记录的安装信息
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct InstallInfo {
+    /// 来源 bucket,直接从 URL 或本地文件安装时没有
+    pub bucket: Option<String>,
+    pub architecture: Option<String>,
+    /// 直接从 URL 安装时的 manifest 地址
+    pub url: Option<String>,
+    /// 已用 `scoop hold` 锁定版本
+    pub hold: bool,
+}
+
+impl InstallInfo {
+    /// 读取已安装应用目录(`apps\<app>\current`)中的 `install.json`,不存在或无法解析时返回 None
+    pub fn read(app_dir: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(app_dir.join("install.json")).ok()?;
+        serde_json::from_str(content.trim_start_matches('\u{feff}')).ok()
+    }
+}
+
+/// bucket 中某个应用的 manifest 路径(不检查是否存在)
+pub fn bucket_manifest_path(root: &Path, bucket: &str, app_name: &str) -> PathBuf {
+    root.join("buckets")
+        .join(bucket)
+        .join("bucket")
+        .join(format!("{}.json", app_name))
+}
+
+/// 在所有 bucket 中查找应用的 manifest,返回路径和 bucket 名
+pub fn find_in_buckets(root: &Path, app_name: &str) -> Option<(PathBuf, String)> {
+    let entries = std::fs::read_dir(root.join("buckets")).ok()?;
+    entries.flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let bucket = entry.file_name().to_string_lossy().to_string();
+            (bucket_manifest_path(root, &bucket, app_name), bucket)
+        })
+        .find(|(path, _)| path.exists())
+}
+
+/// 既可以是单个值也可以是数组的字段
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    Many(Vec<T>),
+    One(T),
+}
+
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(values) => values,
+        OneOrMany::One(value) => vec![value],
+    })
+}
+
+fn suggest_map<'de, D>(deserializer: D) -> Result<BTreeMap<String, Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let map = BTreeMap::<String, OneOrMany<String>>::deserialize(deserializer)?;
+    Ok(map.into_iter()
+        .map(|(feature, apps)| match apps {
+            OneOrMany::Many(apps) => (feature, apps),
+            OneOrMany::One(app) => (feature, vec![app]),
+        })
+        .collect())
+}
+
+/// `bin`、`persist` 中的一项:一个路径,或者以路径开头的数组
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PathOrList {
+    Path(String),
+    List(Vec<String>),
+}
+
+impl PathOrList {
+    fn into_parts(self) -> Result<Vec<String>, String> {
+        match self {
+            PathOrList::Path(path) => Ok(vec![path]),
+            PathOrList::List(parts) if !parts.is_empty() => Ok(parts),
+            PathOrList::List(_) => Err("expected a path".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> Manifest {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("manifests")
+            .join(format!("{}.json", name));
+        Manifest::read(&path).unwrap()
+    }
+
+    #[test]
+    fn parses_7zip() {
+        let manifest = fixture("7zip");
+        assert_eq!(manifest.version, "23.01");
+        assert_eq!(manifest.license_identifier(), Some("LGPL-2.1-or-later"));
+        assert_eq!(manifest.notes.len(), 1);
+        assert_eq!(manifest.bin.iter().map(Bin::shim_name).collect::<Vec<_>>(), ["7z", "7zFM", "7zG"]);
+        assert_eq!(manifest.shortcuts[0].name, "7-Zip");
+        assert_eq!(manifest.persist, [
+            Persist { source: "Codecs".to_string(), target: None },
+            Persist { source: "Formats".to_string(), target: None },
+        ]);
+        assert_eq!(manifest.post_install.len(), 9);
+
+        let architecture = manifest.architecture.as_ref().unwrap();
+        assert_eq!(architecture.names(), ["64bit", "32bit", "arm64"]);
+        assert_eq!(architecture.get("64bit").unwrap().extract_dir, ["Files\\7-Zip"]);
+        assert_eq!(architecture.get("arm64").unwrap().pre_install.len(), 4);
+
+        let Some(Checkver::Detailed(checkver)) = &manifest.checkver else { panic!("expected a checkver object") };
+        assert_eq!(checkver.url.as_deref(), Some("https://www.7-zip.org/download.html"));
+        let autoupdate = manifest.autoupdate.as_ref().unwrap();
+        assert_eq!(
+            autoupdate.architecture.as_ref().unwrap().get("64bit").unwrap().url,
+            ["https://www.7-zip.org/a/7z$cleanVersion-x64.msi"]
+        );
+    }
+
+    #[test]
+    fn parses_git_bin_aliases_and_env() {
+        let manifest = fixture("git");
+        assert_eq!(manifest.license, Some(License::Identifier("GPL-2.0-only".to_string())));
+        assert_eq!(manifest.suggest["vimtutor"], ["vim"]);
+        assert_eq!(manifest.bin.len(), 6);
+        assert_eq!(manifest.bin[4], Bin { path: "git-bash.exe".to_string(), alias: Some("git-bash".to_string()), args: None });
+        assert_eq!(manifest.bin[5].args.as_deref(), Some("--login -i"));
+        assert_eq!(manifest.bin[5].shim_name(), "sh");
+        assert_eq!(manifest.shortcuts[0].args.as_deref(), Some("--cd-to-home"));
+        assert_eq!(manifest.env_set["GIT_INSTALL_ROOT"], "$dir");
+
+        let Some(Checkver::Detailed(checkver)) = &manifest.checkver else { panic!("expected a checkver object") };
+        assert_eq!(checkver.jsonpath.as_deref(), Some("$.tag_name"));
+        let hash = &manifest.autoupdate.as_ref().unwrap().hash;
+        assert_eq!(hash[0].regex.as_deref(), Some("$sha256.*?$basename"));
+    }
+
+    #[test]
+    fn parses_installer_scripts() {
+        let manifest = fixture("python");
+        assert_eq!(manifest.notes.len(), 2);
+        assert_eq!(manifest.suggest["Visual C++ Redistributable"], ["extras/vcredist2022"]);
+        assert_eq!(manifest.installer.as_ref().unwrap().script.len(), 3);
+        assert_eq!(manifest.uninstaller.as_ref().unwrap().script.len(), 1);
+        assert_eq!(manifest.pre_install.len(), 2);
+        assert_eq!(manifest.env_add_path, ["Scripts", "."]);
+        let icon = &manifest.shortcuts[0];
+        assert_eq!(icon.icon.as_deref(), Some("DLLs\\idle.ico"));
+        assert_eq!(manifest.architecture.unwrap().x86.unwrap().hash, ["md5:2b1ef5a8d4c4b06a1a1f8e8a0c1d9b7e"]);
+    }
+
+    #[test]
+    fn parses_license_object_and_persist_pairs() {
+        let manifest = fixture("vscode");
+        assert_eq!(manifest.license_identifier(), Some("Freeware"));
+        assert_eq!(manifest.bin, [Bin { path: "bin\\code.cmd".to_string(), alias: None, args: None }]);
+        assert_eq!(manifest.persist[1], Persist {
+            source: "user-data".to_string(),
+            target: Some("data\\user-data".to_string()),
+        });
+        let autoupdate = manifest.autoupdate.unwrap().architecture.unwrap();
+        assert_eq!(autoupdate.get("arm64").unwrap().hash[0].jsonpath.as_deref(), Some("$.sha256hash"));
+        assert!(autoupdate.get("32bit").is_none());
+    }
+
+    #[test]
+    fn collects_bins_from_architecture_blocks() {
+        let manifest = fixture("neovim");
+        assert_eq!(manifest.depends, ["main/7zip", "extras/vcredist2022"]);
+        assert_eq!(manifest.env_add_path, ["bin"]);
+        assert!(matches!(manifest.checkver, Some(Checkver::Regex(ref s)) if s == "github"));
+        assert!(manifest.bin.is_empty());
+        let shims: Vec<String> = manifest.bins().into_iter().map(Bin::shim_name).collect();
+        assert_eq!(shims, ["nvim", "nvim-qt"]);
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        assert!(Manifest::parse("\u{feff}{\"version\": \"1.0\"}").is_ok());
+        assert!(Manifest::parse("{\"version\": \"1.0\", \"bin\": [[]]}").is_err());
+        assert!(Manifest::parse("{\"version\": \"1.0\", \"shortcuts\": [[\"app.exe\"]]}").is_err());
+        assert!(Manifest::parse("not json").is_err());
+    }
+}
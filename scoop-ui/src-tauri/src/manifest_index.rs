@@ -0,0 +1,522 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::UNIX_EPOCH;
+use tauri::State;
+
+use crate::error::AppError;
+use crate::manifest::{Bin, Manifest};
+use crate::paths;
+
+/// 索引文件的格式版本,结构变化时加一,旧的索引会被丢弃重建
+const INDEX_VERSION: u32 = 3;
+
+/// 保存索引失败时发送给界面的事件,负载为 [`AppError`]
+pub const INDEX_ERROR_EVENT: &str = "index-error";
+
+/// 报告保存失败的回调
+type ErrorReporter = Box<dyn Fn(AppError) + Send + Sync>;
+
+/// 查找可执行文件时忽略的扩展名
+const EXECUTABLE_EXTENSIONS: [&str; 7] = [".exe", ".cmd", ".bat", ".ps1", ".com", ".jar", ".lnk"];
+
+/// 索引中的一个应用,只保留搜索、发现页需要的字段
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub name: String,
+    pub bucket: String,
+    pub version: String,
+    pub description: String,
+    pub homepage: String,
+    pub license: Option<String>,
+    /// 应用创建的 shim 名
+    pub bins: Vec<String>,
+    /// 应用提供的所有 shim 和快捷方式,用于按可执行文件反查应用
+    pub binaries: Vec<Binary>,
+    /// `architecture` 中列出的架构名,为空表示与架构无关
+    pub architectures: Vec<String>,
+    /// manifest 文件的修改时间,Unix 毫秒
+    pub mtime: u64,
+}
+
+/// 应用提供的一个入口
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Binary {
+    pub kind: BinaryKind,
+    /// shim 名或快捷方式名
+    pub name: String,
+    /// 应用目录中的目标文件,如 `bin\code.cmd`
+    pub target: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryKind {
+    Shim,
+    Shortcut,
+}
+
+impl IndexEntry {
+    fn new(name: String, bucket: String, manifest: Manifest, mtime: u64) -> Self {
+        let shims = manifest.bins().into_iter().map(|bin| Binary {
+            kind: BinaryKind::Shim,
+            name: bin.shim_name(),
+            target: bin.path.clone(),
+        });
+        let shortcuts = manifest.all_shortcuts().into_iter().map(|shortcut| Binary {
+            kind: BinaryKind::Shortcut,
+            name: shortcut.name.clone(),
+            target: shortcut.target.clone(),
+        });
+        IndexEntry {
+            license: manifest.license_identifier().map(str::to_string),
+            bins: manifest.bins().into_iter().map(Bin::shim_name).collect(),
+            binaries: shims.chain(shortcuts).collect(),
+            architectures: manifest.architecture.as_ref()
+                .map(|architectures| architectures.names().into_iter().map(str::to_string).collect())
+                .unwrap_or_default(),
+            name,
+            bucket,
+            version: manifest.version,
+            description: manifest.description,
+            homepage: manifest.homepage,
+            mtime,
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct BucketIndex {
+    /// 建立索引时 bucket 仓库的 HEAD,没有变化时跳过整个 bucket
+    head: Option<String>,
+    /// 应用名 -> 条目
+    entries: BTreeMap<String, IndexEntry>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct IndexData {
+    version: u32,
+    /// 建立索引时的 scoop 根目录,根目录变化时重建
+    root: PathBuf,
+    buckets: BTreeMap<String, BucketIndex>,
+}
+
+struct IndexState {
+    data: IndexData,
+    /// 所有 bucket 的条目,按 bucket、应用名排序,索引变化时重新生成
+    snapshot: Arc<Vec<IndexEntry>>,
+    /// [`binary_key`] -> 提供它的条目在 `snapshot` 中的下标
+    by_binary: HashMap<String, Vec<usize>>,
+}
+
+impl IndexState {
+    fn rebuild(&mut self) {
+        let snapshot = flatten(&self.data);
+        let mut by_binary: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, entry) in snapshot.iter().enumerate() {
+            for binary in &entry.binaries {
+                for key in [binary_key(&binary.name), binary_key(&binary.target)] {
+                    let providers = by_binary.entry(key).or_default();
+                    if providers.last() != Some(&i) {
+                        providers.push(i);
+                    }
+                }
+            }
+        }
+        self.snapshot = Arc::new(snapshot);
+        self.by_binary = by_binary;
+    }
+}
+
+/// 所有 bucket 中 manifest 的索引,搜索、发现页和详情查找共用
+///
+/// 索引保存在应用数据目录下,启动后直接可用。每次读取前按需刷新:
+/// 仓库 HEAD 没有变化的 bucket 直接跳过,其余 bucket 只重新解析修改时间变化了的 manifest。
+pub struct ManifestIndex {
+    file: Option<PathBuf>,
+    state: Mutex<IndexState>,
+    on_error: OnceLock<ErrorReporter>,
+}
+
+impl ManifestIndex {
+    /// 读取上次保存的索引,文件不存在或格式版本不符时从空索引开始
+    pub fn open(file: PathBuf) -> Self {
+        let data = std::fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str::<IndexData>(&content).ok())
+            .filter(|data| data.version == INDEX_VERSION)
+            .unwrap_or_default();
+        Self::with_data(Some(file), data)
+    }
+
+    /// 不保存到磁盘的索引
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self::with_data(None, IndexData::default())
+    }
+
+    fn with_data(file: Option<PathBuf>, data: IndexData) -> Self {
+        let mut state = IndexState {
+            data,
+            snapshot: Arc::default(),
+            by_binary: HashMap::new(),
+        };
+        state.rebuild();
+        Self {
+            file,
+            state: Mutex::new(state),
+            on_error: OnceLock::new(),
+        }
+    }
+
+    /// 设置保存索引失败时的回调。保存失败不影响使用,只是下次启动要重新解析所有 manifest
+    pub fn on_error(&self, report: impl Fn(AppError) + Send + Sync + 'static) {
+        let _ = self.on_error.set(Box::new(report));
+    }
+
+    /// 刷新索引并返回 `root` 下所有 bucket 的条目
+    pub fn entries(&self, root: &Path) -> Arc<Vec<IndexEntry>> {
+        self.refreshed(root).snapshot.clone()
+    }
+
+    /// 提供某个可执行文件(shim 名、bin 文件名或快捷方式)的所有条目,以及匹配到的入口
+    ///
+    /// 比较时忽略大小写、目录和 `.exe` 等扩展名,所以 `rg`、`rg.exe` 都能找到 ripgrep。
+    pub fn find_by_binary(&self, root: &Path, name: &str) -> Vec<(IndexEntry, Vec<Binary>)> {
+        let key = binary_key(name);
+        let state = self.refreshed(root);
+        let Some(providers) = state.by_binary.get(&key) else {
+            return Vec::new();
+        };
+        providers.iter()
+            .map(|&i| {
+                let entry = state.snapshot[i].clone();
+                let matches = entry.binaries.iter()
+                    .filter(|binary| binary_key(&binary.name) == key || binary_key(&binary.target) == key)
+                    .cloned()
+                    .collect();
+                (entry, matches)
+            })
+            .collect()
+    }
+
+    fn refreshed(&self, root: &Path) -> MutexGuard<'_, IndexState> {
+        let mut state = self.state.lock().unwrap();
+        let mut changed = false;
+        if state.data.version != INDEX_VERSION || state.data.root != root {
+            state.data = IndexData {
+                version: INDEX_VERSION,
+                root: root.to_path_buf(),
+                buckets: BTreeMap::new(),
+            };
+            changed = true;
+        }
+        changed |= refresh(&mut state.data, root);
+
+        if changed {
+            state.rebuild();
+            self.save(&state.data);
+        }
+        state
+    }
+
+    /// 按名字查找应用(不区分大小写),多个 bucket 都有时返回按 bucket 名排序的第一个
+    pub fn find(&self, root: &Path, app_name: &str) -> Option<IndexEntry> {
+        self.entries(root)
+            .iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(app_name))
+            .cloned()
+    }
+
+    fn save(&self, data: &IndexData) {
+        let Some(file) = &self.file else { return };
+        let result = serde_json::to_string(data)
+            .map_err(|e| AppError::Io { path: Some(file.clone()), message: e.to_string() })
+            .and_then(|content| paths::write_atomic(file, &content).map_err(|e| AppError::io(file, e)));
+        if let (Err(error), Some(report)) = (result, self.on_error.get()) {
+            report(error);
+        }
+    }
+}
+
+/// 统一可执行文件名的写法:只取文件名,小写,去掉 `.exe` 等扩展名
+fn binary_key(name: &str) -> String {
+    let file = name.rsplit(['\\', '/']).next().unwrap_or(name).trim().to_lowercase();
+    EXECUTABLE_EXTENSIONS.iter()
+        .find_map(|ext| file.strip_suffix(ext).filter(|stem| !stem.is_empty()))
+        .map(str::to_string)
+        .unwrap_or(file)
+}
+
+/// 提供某个可执行文件的应用
+#[derive(Serialize)]
+pub struct BinaryProvider {
+    pub name: String,
+    pub bucket: String,
+    pub version: String,
+    pub description: String,
+    /// 是否已安装(当前用户或全局)
+    pub installed: bool,
+    /// 匹配到的 shim 或快捷方式
+    pub matches: Vec<Binary>,
+}
+
+/// 哪些应用提供某个可执行文件,例如 `rg.exe`、`jq` 或 `code`。已安装的排在前面
+#[tauri::command]
+pub fn find_by_binary(index: State<'_, Arc<ManifestIndex>>, name: String) -> Result<Vec<BinaryProvider>, AppError> {
+    let root = crate::scoop_root()?;
+    let global_root = paths::global_root().ok();
+    Ok(providers(&index, &root, global_root.as_deref(), &name))
+}
+
+fn providers(index: &ManifestIndex, root: &Path, global_root: Option<&Path>, name: &str) -> Vec<BinaryProvider> {
+    let is_installed = |app: &str| {
+        std::iter::once(root)
+            .chain(global_root)
+            .any(|root| root.join("apps").join(app).join("current").exists())
+    };
+    let mut providers: Vec<BinaryProvider> = index.find_by_binary(root, name)
+        .into_iter()
+        .map(|(entry, matches)| BinaryProvider {
+            installed: is_installed(&entry.name),
+            name: entry.name,
+            bucket: entry.bucket,
+            version: entry.version,
+            description: entry.description,
+            matches,
+        })
+        .collect();
+    // 稳定排序,同为已安装或未安装时保持 bucket、应用名的顺序
+    providers.sort_by_key(|provider| !provider.installed);
+    providers
+}
+
+fn flatten(data: &IndexData) -> Vec<IndexEntry> {
+    data.buckets
+        .values()
+        .flat_map(|bucket| bucket.entries.values().cloned())
+        .collect()
+}
+
+/// 与磁盘上的 bucket 同步,返回索引是否有变化
+fn refresh(data: &mut IndexData, root: &Path) -> bool {
+    let buckets: Vec<(String, PathBuf)> = std::fs::read_dir(root.join("buckets"))
+        .map(|entries| {
+            entries.flatten()
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // 已删除的 bucket
+    let before = data.buckets.len();
+    data.buckets.retain(|name, _| buckets.iter().any(|(bucket, _)| bucket == name));
+    let mut changed = data.buckets.len() != before;
+
+    for (name, dir) in buckets {
+        let head = git_head(&dir);
+        let index = data.buckets.entry(name.clone()).or_default();
+        if head.is_some() && index.head == head {
+            continue;
+        }
+        changed |= refresh_bucket(index, &name, &dir.join("bucket"));
+        if index.head != head {
+            index.head = head;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// 重新解析修改时间变化的 manifest,删除已不存在的,返回是否有变化
+fn refresh_bucket(index: &mut BucketIndex, bucket: &str, dir: &Path) -> bool {
+    let mut changed = false;
+    let mut seen = HashSet::new();
+
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(name) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) else { continue };
+        let mtime = entry.metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0);
+
+        seen.insert(name.clone());
+        if index.entries.get(&name).is_some_and(|indexed| indexed.mtime == mtime) {
+            continue;
+        }
+        // 无法解析的 manifest 仍然按名字收录,和直接扫描目录时的行为一致
+        let manifest = Manifest::read(&path).unwrap_or_default();
+        index.entries.insert(name.clone(), IndexEntry::new(name, bucket.to_string(), manifest, mtime));
+        changed = true;
+    }
+
+    let before = index.entries.len();
+    index.entries.retain(|name, _| seen.contains(name));
+    changed || index.entries.len() != before
+}
+
+/// bucket 仓库当前的提交,不是 git 仓库或无法读取时返回 None
+fn git_head(bucket_dir: &Path) -> Option<String> {
+    let git_dir = bucket_dir.join(".git");
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    let Some(reference) = head.strip_prefix("ref: ") else {
+        // 分离的 HEAD 直接就是提交
+        return Some(head.to_string());
+    };
+
+    std::fs::read_to_string(git_dir.join(reference))
+        .ok()
+        .map(|commit| commit.trim().to_string())
+        .or_else(|| {
+            // `git gc` 之后引用可能只在 packed-refs 中
+            let packed = std::fs::read_to_string(git_dir.join("packed-refs")).ok()?;
+            packed.lines()
+                .filter_map(|line| line.split_once(' '))
+                .find(|(_, name)| *name == reference)
+                .map(|(commit, _)| commit.to_string())
+        })
+        .filter(|commit| !commit.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixture_root;
+    use std::time::{Duration, SystemTime};
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("scoop-ui-index-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("buckets").join("main").join("bucket")).unwrap();
+        root
+    }
+
+    fn write_manifest(root: &Path, name: &str, version: &str, age_secs: u64) {
+        let path = root.join("buckets").join("main").join("bucket").join(format!("{}.json", name));
+        std::fs::write(&path, format!("{{\"version\": \"{}\", \"bin\": \"{}.exe\"}}", version, name)).unwrap();
+        // 显式设置修改时间,避免两次写入落在同一时刻
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age_secs)).unwrap();
+    }
+
+    fn versions(index: &ManifestIndex, root: &Path) -> Vec<(String, String)> {
+        index.entries(root).iter().map(|entry| (entry.name.clone(), entry.version.clone())).collect()
+    }
+
+    #[test]
+    fn indexes_fixture_buckets() {
+        let index = ManifestIndex::in_memory();
+        let entries = index.entries(&fixture_root());
+        assert_eq!(entries.len(), 5);
+
+        let vscode = index.find(&fixture_root(), "VSCode").unwrap();
+        assert_eq!(vscode.bucket, "extras");
+        assert_eq!(vscode.license.as_deref(), Some("Freeware"));
+        assert_eq!(vscode.bins, ["code"]);
+        assert_eq!(index.find(&fixture_root(), "git").unwrap().bins, ["git", "git-bash"]);
+        assert!(index.find(&fixture_root(), "no-such-app").is_none());
+    }
+
+    #[test]
+    fn finds_apps_by_binary() {
+        let index = ManifestIndex::in_memory();
+        let root = fixture_root();
+        let names = |name: &str| -> Vec<(String, bool)> {
+            providers(&index, &root, None, name).into_iter().map(|p| (p.name, p.installed)).collect()
+        };
+
+        // 已安装的 nodejs 排在未安装的 nodejs-lts 前面
+        assert_eq!(names("node.exe"), [("nodejs".to_string(), true), ("nodejs-lts".to_string(), false)]);
+        assert_eq!(names("7Z"), [("7zip".to_string(), true)]);
+        // 别名和目标文件名都能找到
+        assert_eq!(names("git-bash"), [("git".to_string(), true)]);
+        assert_eq!(names("usr\\bin\\bash.exe"), [("git".to_string(), true)]);
+        assert!(names("rg").is_empty());
+
+        let code = providers(&index, &root, None, "code");
+        assert_eq!(code.len(), 1);
+        assert_eq!(code[0].matches, [
+            Binary { kind: BinaryKind::Shim, name: "code".to_string(), target: "bin\\code.cmd".to_string() },
+            Binary { kind: BinaryKind::Shortcut, name: "Visual Studio Code".to_string(), target: "Code.exe".to_string() },
+        ]);
+        assert_eq!(names("Visual Studio Code"), [("vscode".to_string(), false)]);
+    }
+
+    #[test]
+    fn refreshes_changed_manifests_by_mtime() {
+        let root = temp_root("mtime");
+        write_manifest(&root, "alpha", "1.0", 100);
+        write_manifest(&root, "beta", "2.0", 100);
+        let index = ManifestIndex::in_memory();
+        assert_eq!(versions(&index, &root), [("alpha".into(), "1.0".into()), ("beta".into(), "2.0".into())]);
+
+        write_manifest(&root, "alpha", "1.1", 50);
+        std::fs::remove_file(root.join("buckets").join("main").join("bucket").join("beta.json")).unwrap();
+        assert_eq!(versions(&index, &root), [("alpha".into(), "1.1".into())]);
+
+        std::fs::remove_dir_all(root.join("buckets").join("main")).unwrap();
+        assert!(index.entries(&root).is_empty());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn skips_buckets_whose_head_did_not_move() {
+        let root = temp_root("head");
+        let git_dir = root.join("buckets").join("main").join(".git");
+        std::fs::create_dir_all(git_dir.join("refs").join("heads")).unwrap();
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/master\n").unwrap();
+        std::fs::write(git_dir.join("packed-refs"), "# pack-refs with: peeled\naaaa refs/heads/master\n").unwrap();
+        write_manifest(&root, "alpha", "1.0", 100);
+
+        let index = ManifestIndex::in_memory();
+        assert_eq!(versions(&index, &root), [("alpha".into(), "1.0".into())]);
+
+        // HEAD 没变时不会重新扫描
+        write_manifest(&root, "alpha", "1.1", 50);
+        assert_eq!(versions(&index, &root), [("alpha".into(), "1.0".into())]);
+
+        std::fs::write(git_dir.join("refs").join("heads").join("master"), "bbbb\n").unwrap();
+        assert_eq!(versions(&index, &root), [("alpha".into(), "1.1".into())]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn persists_between_sessions() {
+        let root = temp_root("persist");
+        write_manifest(&root, "alpha", "1.0", 100);
+        let file = root.join("manifest-index.json");
+        assert_eq!(ManifestIndex::open(file.clone()).entries(&root).len(), 1);
+
+        let reopened = ManifestIndex::open(file);
+        assert_eq!(reopened.state.lock().unwrap().snapshot.len(), 1);
+        assert_eq!(versions(&reopened, &root), [("alpha".into(), "1.0".into())]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn reports_save_failures() {
+        let root = temp_root("save-error");
+        write_manifest(&root, "alpha", "1.0", 100);
+        let file = root.join("missing").join("manifest-index.json");
+        let index = ManifestIndex::open(file.clone());
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let reported = errors.clone();
+        index.on_error(move |error| reported.lock().unwrap().push(error));
+
+        // 保存失败时索引照常可用
+        assert_eq!(index.entries(&root).len(), 1);
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], AppError::Io { path: Some(path), .. } if *path == file));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::AppError;
+
+/// 用户在界面中指定的 scoop 根目录,优先于其他所有来源
+static ROOT_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// 保存界面设置的文件,在 [`init`] 中确定
+static SETTINGS_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// scoop 根目录是从哪里得到的
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RootSource {
+    /// 界面中的设置
+    Override,
+    /// `SCOOP` / `SCOOP_GLOBAL` 环境变量
+    Env,
+    /// scoop 配置文件中的 `root_path` / `global_path`
+    Config,
+    /// `%USERPROFILE%\scoop` / `%ProgramData%\scoop`
+    Default,
+}
+
+/// 应用安装在当前用户下还是全局(`scoop install -g`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    #[default]
+    User,
+    Global,
+}
+
+impl Scope {
+    /// 该范围的应用所在的根目录
+    pub fn root(self) -> Result<PathBuf, String> {
+        match self {
+            Scope::User => scoop_root(),
+            Scope::Global => global_root(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ScoopRoot {
+    pub path: String,
+    pub source: RootSource,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Settings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scoop_root: Option<String>,
+}
+
+/// 读取保存的设置,应用启动时调用一次
+pub fn init(app_data_dir: &Path) {
+    let file = app_data_dir.join("settings.json");
+    let settings: Settings = std::fs::read_to_string(&file)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    *ROOT_OVERRIDE.lock().unwrap() = settings.scoop_root.map(PathBuf::from);
+    let _ = SETTINGS_FILE.set(file);
+}
+
+/// scoop 的安装根目录(包含 `apps`、`buckets` 等)
+///
+/// 与 scoop 自身的规则一致:`SCOOP` 环境变量 > 配置文件的 `root_path` > `%USERPROFILE%\scoop`,
+/// 界面中的设置优先于这三者。
+pub fn scoop_root() -> Result<PathBuf, String> {
+    resolve_root().map(|(path, _)| path)
+}
+
+pub fn resolve_root() -> Result<(PathBuf, RootSource), String> {
+    let root_override = ROOT_OVERRIDE.lock().unwrap().clone();
+    let home = home_dir();
+    let config = home.as_deref().and_then(|home| read_config(&config_file(home)));
+    resolve(
+        root_override,
+        std::env::var_os("SCOOP").map(PathBuf::from),
+        config.and_then(|config| config.root_path),
+        home,
+    )
+    .ok_or("Failed to locate the Scoop root: USERPROFILE is not set".to_string())
+}
+
+/// 按优先级选出第一个非空的来源
+fn resolve(
+    root_override: Option<PathBuf>,
+    env: Option<PathBuf>,
+    config: Option<String>,
+    home: Option<PathBuf>,
+) -> Option<(PathBuf, RootSource)> {
+    let non_empty = |path: &PathBuf| !path.as_os_str().is_empty();
+    root_override.filter(non_empty).map(|path| (path, RootSource::Override))
+        .or_else(|| env.filter(non_empty).map(|path| (path, RootSource::Env)))
+        .or_else(|| config.filter(|path| !path.is_empty()).map(|path| (PathBuf::from(path), RootSource::Config)))
+        .or_else(|| home.map(|home| (home.join("scoop"), RootSource::Default)))
+}
+
+/// 全局安装的根目录:`SCOOP_GLOBAL` 环境变量 > 配置文件的 `global_path` > `%ProgramData%\scoop`
+pub fn global_root() -> Result<PathBuf, String> {
+    resolve_global_root().map(|(path, _)| path)
+}
+
+pub fn resolve_global_root() -> Result<(PathBuf, RootSource), String> {
+    let config = home_dir().and_then(|home| read_config(&config_file(&home)));
+    let program_data = std::env::var_os("ProgramData")
+        .or_else(|| std::env::var_os("ALLUSERSPROFILE"))
+        .map(PathBuf::from);
+    resolve_global(
+        std::env::var_os("SCOOP_GLOBAL").map(PathBuf::from),
+        config.and_then(|config| config.global_path),
+        program_data,
+    )
+    .ok_or("Failed to locate the global Scoop root: ProgramData is not set".to_string())
+}
+
+fn resolve_global(
+    env: Option<PathBuf>,
+    config: Option<String>,
+    program_data: Option<PathBuf>,
+) -> Option<(PathBuf, RootSource)> {
+    resolve(None, env, config, None)
+        .or_else(|| program_data.map(|dir| (dir.join("scoop"), RootSource::Default)))
+}
+
+/// 当前进程是否有管理员权限。全局安装、更新和卸载都需要管理员权限
+///
+/// 结果在第一次调用后缓存。非 Windows 平台上没有 UAC,始终返回 true。
+pub fn is_elevated() -> bool {
+    static ELEVATED: OnceLock<bool> = OnceLock::new();
+    *ELEVATED.get_or_init(|| {
+        if cfg!(windows) {
+            // `net session` 只有管理员才能执行成功
+            crate::process::command("net")
+                .arg("session")
+                .output()
+                .map(|output| output.status.success())
+                .unwrap_or(false)
+        } else {
+            true
+        }
+    })
+}
+
+/// 对某个范围执行修改操作前检查权限,没有权限时返回 [`AppError::ElevationRequired`]
+pub fn check_elevation(scope: Scope) -> Result<(), AppError> {
+    if scope == Scope::Global && !is_elevated() {
+        return Err(AppError::ElevationRequired);
+    }
+    Ok(())
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("USERPROFILE")
+        .or_else(|| std::env::var_os("HOME"))
+        .map(PathBuf::from)
+}
+
+/// scoop 的配置文件:`$XDG_CONFIG_HOME\scoop\config.json`,默认 `~\.config\scoop\config.json`
+fn config_file(home: &Path) -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".config"))
+        .join("scoop")
+        .join("config.json")
+}
+
+/// scoop 配置文件中我们关心的字段
+#[derive(Default, Deserialize)]
+struct ScoopConfig {
+    root_path: Option<String>,
+    global_path: Option<String>,
+}
+
+fn read_config(file: &Path) -> Option<ScoopConfig> {
+    let content = std::fs::read_to_string(file).ok()?;
+    // scoop 用 PowerShell 写入配置文件,可能带 BOM
+    serde_json::from_str(content.trim_start_matches('\u{feff}')).ok()
+}
+
+/// 获取当前使用的 scoop 根目录及其来源
+#[tauri::command]
+pub fn get_scoop_root() -> Result<ScoopRoot, AppError> {
+    let (path, source) = resolve_root().map_err(AppError::ScoopNotFound)?;
+    Ok(ScoopRoot {
+        path: path.to_string_lossy().to_string(),
+        source,
+    })
+}
+
+/// 获取全局安装的根目录及其来源
+#[tauri::command]
+pub fn get_global_root() -> Result<ScoopRoot, AppError> {
+    let (path, source) = resolve_global_root().map_err(AppError::ScoopNotFound)?;
+    Ok(ScoopRoot {
+        path: path.to_string_lossy().to_string(),
+        source,
+    })
+}
+
+/// 当前是否以管理员身份运行,界面据此提示全局操作需要提权
+#[tauri::command]
+pub fn is_elevated_process() -> bool {
+    is_elevated()
+}
+
+/// 先写临时文件再改名,中途退出不会留下写了一半的文件
+pub fn write_atomic(file: &Path, content: &str) -> std::io::Result<()> {
+    let mut tmp = file.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, file)
+}
+
+/// 设置(或用 None 清除)界面中的 scoop 根目录,设置会保存下来
+#[tauri::command]
+pub fn set_scoop_root(path: Option<String>) -> Result<ScoopRoot, AppError> {
+    let path = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(ref dir) = path {
+        let dir = Path::new(dir);
+        if !dir.join("apps").is_dir() && !dir.join("buckets").is_dir() {
+            return Err(AppError::InvalidRoot {
+                path: dir.to_path_buf(),
+                reason: "no apps or buckets directory".to_string(),
+            });
+        }
+    }
+
+    if let Some(file) = SETTINGS_FILE.get() {
+        let settings = Settings { scoop_root: path.clone() };
+        let content = serde_json::to_string_pretty(&settings)
+            .map_err(|e| AppError::Io { path: Some(file.clone()), message: e.to_string() })?;
+        if let Some(parent) = file.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        write_atomic(file, &content).map_err(|e| AppError::io(file, e))?;
+    }
+    *ROOT_OVERRIDE.lock().unwrap() = path.map(PathBuf::from);
+
+    get_scoop_root()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_scoop_precedence() {
+        let home = Some(PathBuf::from("C:\\Users\\dev"));
+        let all = |o: Option<&str>, e: Option<&str>, c: Option<&str>| {
+            resolve(o.map(PathBuf::from), e.map(PathBuf::from), c.map(String::from), home.clone())
+        };
+
+        assert_eq!(all(Some("E:\\ui"), Some("D:\\env"), Some("F:\\cfg")), Some((PathBuf::from("E:\\ui"), RootSource::Override)));
+        assert_eq!(all(None, Some("D:\\env"), Some("F:\\cfg")), Some((PathBuf::from("D:\\env"), RootSource::Env)));
+        assert_eq!(all(None, None, Some("F:\\cfg")), Some((PathBuf::from("F:\\cfg"), RootSource::Config)));
+        assert_eq!(all(None, Some(""), Some("")), Some((PathBuf::from("C:\\Users\\dev").join("scoop"), RootSource::Default)));
+        assert_eq!(resolve(None, None, None, None), None);
+    }
+
+    #[test]
+    fn resolves_global_root() {
+        let program_data = Some(PathBuf::from("C:\\ProgramData"));
+        assert_eq!(
+            resolve_global(Some(PathBuf::from("D:\\global")), Some("E:\\cfg".to_string()), program_data.clone()),
+            Some((PathBuf::from("D:\\global"), RootSource::Env))
+        );
+        assert_eq!(
+            resolve_global(None, Some("E:\\cfg".to_string()), program_data.clone()),
+            Some((PathBuf::from("E:\\cfg"), RootSource::Config))
+        );
+        assert_eq!(
+            resolve_global(None, None, program_data),
+            Some((PathBuf::from("C:\\ProgramData").join("scoop"), RootSource::Default))
+        );
+    }
+
+    #[test]
+    fn reads_root_path_from_config() {
+        let dir = std::env::temp_dir().join(format!("scoop-ui-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("config.json");
+        std::fs::write(&file, "\u{feff}{\"root_path\": \"D:\\\\scoop\", \"global_path\": \"D:\\\\global\", \"last_update\": \"2024-01-05\"}").unwrap();
+
+        let config = read_config(&file).unwrap();
+        assert_eq!(config.root_path.as_deref(), Some("D:\\scoop"));
+        assert_eq!(config.global_path.as_deref(), Some("D:\\global"));
+        assert!(read_config(&dir.join("missing.json")).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn writes_files_atomically() {
+        let dir = std::env::temp_dir().join(format!("scoop-ui-write-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("settings.json");
+        write_atomic(&file, "{}").unwrap();
+        write_atomic(&file, "{\"scoop_root\": \"D:\\\\scoop\"}").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "{\"scoop_root\": \"D:\\\\scoop\"}");
+        assert!(!dir.join("settings.json.tmp").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,32 @@
+use std::ffi::OsStr;
+use std::process::Command;
+
+/// Windows 下创建子进程时不弹出控制台窗口
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// 创建子进程命令。所有子进程都应通过这里创建,Windows 下不会弹出控制台窗口
+pub fn command(program: impl AsRef<OsStr>) -> Command {
+    #[allow(unused_mut)]
+    let mut command = Command::new(program);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command
+}
+
+/// 结束进程及其启动的所有子进程(scoop 会启动 aria2/7z/安装程序等)
+pub fn kill_process_tree(pid: u32) {
+    #[cfg(windows)]
+    let _ = command("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .output();
+
+    #[cfg(not(windows))]
+    let _ = command("pkill")
+        .args(["-KILL", "-P", &pid.to_string()])
+        .output()
+        .and_then(|_| command("kill").args(["-KILL", &pid.to_string()]).output());
+}
@@ -0,0 +1,422 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::process::Command;
+
+use crate::paths::{self, Scope};
+use crate::process;
+
+/// 传递参数的环境变量,内容为 JSON 字符串数组
+const ARGS_ENV: &str = "SCOOP_UI_ARGS";
+
+/// 根目录下 scoop.ps1 的路径,找不到时脚本退回到 PATH 中的 `scoop`
+const SCOOP_SCRIPT_ENV: &str = "SCOOP_UI_SCOOP";
+
+/// 每个脚本前都会加上这一句,把环境变量中的参数取到 `$argv`,要执行的 scoop 取到 `$scoop`
+///
+/// 参数始终作为数据传入,脚本本身是常量,所以参数中的 `;`、`$()` 等字符不会被 PowerShell 当作代码执行。
+/// `ForEach-Object` 用于展开 Windows PowerShell 5.1 中 ConvertFrom-Json 不会自动展开的数组。
+const ARGV_PRELUDE: &str = "$argv = @($env:SCOOP_UI_ARGS | ConvertFrom-Json | ForEach-Object { $_ }); \
+    $scoop = if ($env:SCOOP_UI_SCOOP) { $env:SCOOP_UI_SCOOP } else { 'scoop' }; ";
+
+/// 执行的脚本:把参数原样传给 scoop
+const RUN_SCOOP: &str = "& $scoop @argv";
+
+/// 命令参数校验失败,携带被拒绝的原始值
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidArgument {
+    AppName(String),
+    BucketName(String),
+    BucketUrl(String),
+    Query(String),
+    Version(String),
+}
+
+impl fmt::Display for InvalidArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidArgument::AppName(name) => write!(f, "Invalid app name: {:?}", name),
+            InvalidArgument::BucketName(name) => write!(f, "Invalid bucket name: {:?}", name),
+            InvalidArgument::BucketUrl(url) => write!(f, "Invalid bucket URL: {:?}", url),
+            InvalidArgument::Query(query) => write!(f, "Invalid search query: {:?}", query),
+            InvalidArgument::Version(version) => write!(f, "Invalid version: {:?}", version),
+        }
+    }
+}
+
+impl std::error::Error for InvalidArgument {}
+
+/// `scoop install --arch` 接受的架构
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Architecture {
+    #[serde(rename = "64bit")]
+    X64,
+    #[serde(rename = "32bit")]
+    X86,
+    #[serde(rename = "arm64")]
+    Arm64,
+}
+
+impl Architecture {
+    /// scoop 的架构名,与 manifest 中 `architecture` 的键相同
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Architecture::X64 => "64bit",
+            Architecture::X86 => "32bit",
+            Architecture::Arm64 => "arm64",
+        }
+    }
+}
+
+/// `scoop install` 的选项,没有给出的字段取默认值
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstallOptions {
+    /// `--arch`,不指定时由 scoop 按系统选择
+    pub arch: Option<Architecture>,
+    /// `--global`
+    pub global: bool,
+    /// `--no-cache`,不使用已下载的缓存
+    pub no_cache: bool,
+    /// `--skip-hash-check`,不校验下载文件的哈希
+    pub skip_hash_check: bool,
+    /// `--independent`,不自动安装依赖
+    pub independent: bool,
+}
+
+impl InstallOptions {
+    pub fn scope(&self) -> Scope {
+        if self.global { Scope::Global } else { Scope::User }
+    }
+
+    /// 除 `--global` 以外的命令行参数,`--global` 由 [`ScoopCommand::app_command`] 按范围添加
+    fn flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if let Some(arch) = self.arch {
+            flags.extend(["--arch".to_string(), arch.as_str().to_string()]);
+        }
+        for (enabled, flag) in [
+            (self.no_cache, "--no-cache"),
+            (self.skip_hash_check, "--skip-hash-check"),
+            (self.independent, "--independent"),
+        ] {
+            if enabled {
+                flags.push(flag.to_string());
+            }
+        }
+        flags
+    }
+}
+
+impl fmt::Display for InstallOptions {
+    /// 用于操作结果,例如 `--global --arch 32bit --no-cache`,全部为默认值时为空
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let global = self.global.then(|| "--global".to_string());
+        let flags: Vec<String> = global.into_iter().chain(self.flags()).collect();
+        write!(f, "{}", flags.join(" "))
+    }
+}
+
+/// 一条经过校验的 scoop 调用
+///
+/// 所有调用 scoop 的地方都通过这里构造命令,不要自己拼接 PowerShell 脚本。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoopCommand {
+    args: Vec<String>,
+}
+
+impl ScoopCommand {
+    fn scoop(args: Vec<String>) -> Self {
+        Self { args }
+    }
+
+    /// `scoop <subcommand> <app>`,全局范围时加上 `--global`
+    fn app_command(subcommand: &str, app: &str, scope: Scope) -> Result<Self, InvalidArgument> {
+        let mut args = vec![subcommand.to_string(), validate_app(app)?];
+        if scope == Scope::Global {
+            args.push("--global".to_string());
+        }
+        Ok(Self::scoop(args))
+    }
+
+    /// `scoop install <app>`,`app` 可以带 `bucket/` 前缀
+    ///
+    /// 指定 `version` 时安装 `<app>@<version>`,scoop 用 manifest 的 autoupdate 生成该版本的 manifest 再安装。
+    pub fn install_with(app: &str, version: Option<&str>, options: &InstallOptions) -> Result<Self, InvalidArgument> {
+        let mut command = Self::app_command("install", app, options.scope())?;
+        if let Some(version) = version {
+            command.args[1] = format!("{}@{}", command.args[1], validate_version(version)?);
+        }
+        command.args.extend(options.flags());
+        Ok(command)
+    }
+
+    pub fn uninstall(app: &str, scope: Scope) -> Result<Self, InvalidArgument> {
+        Self::app_command("uninstall", app, scope)
+    }
+
+    pub fn update(app: &str, scope: Scope) -> Result<Self, InvalidArgument> {
+        Self::app_command("update", app, scope)
+    }
+
+    /// `scoop hold <app>`,锁定版本,之后的 `scoop update` 会跳过它
+    pub fn hold(app: &str, scope: Scope) -> Result<Self, InvalidArgument> {
+        Self::app_command("hold", app, scope)
+    }
+
+    pub fn unhold(app: &str, scope: Scope) -> Result<Self, InvalidArgument> {
+        Self::app_command("unhold", app, scope)
+    }
+
+    /// `scoop update`,更新 scoop 本身和所有 bucket
+    pub fn update_scoop() -> Self {
+        Self::scoop(vec!["update".to_string()])
+    }
+
+    pub fn bucket_add(name: &str, url: Option<&str>) -> Result<Self, InvalidArgument> {
+        let mut args = vec!["bucket".to_string(), "add".to_string(), validate_bucket(name)?];
+        if let Some(url) = url {
+            args.push(validate_bucket_url(url)?);
+        }
+        Ok(Self::scoop(args))
+    }
+
+    pub fn bucket_rm(name: &str) -> Result<Self, InvalidArgument> {
+        Ok(Self::scoop(vec!["bucket".to_string(), "rm".to_string(), validate_bucket(name)?]))
+    }
+
+    /// 生成可以直接执行的 [`Command`],不会弹出控制台窗口
+    ///
+    /// 子进程的 `SCOOP`/`SCOOP_GLOBAL` 环境变量设为界面使用的根目录,保证 scoop 操作的是界面显示的那个目录。
+    pub fn to_command(&self) -> Command {
+        let script = format!("{}{}", ARGV_PRELUDE, RUN_SCOOP);
+        let mut command = process::command("powershell");
+        command
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .env(ARGS_ENV, serde_json::to_string(&self.args).unwrap_or_default());
+        if let Ok(root) = paths::scoop_root() {
+            let scoop_script = root.join("apps").join("scoop").join("current").join("bin").join("scoop.ps1");
+            if scoop_script.exists() {
+                command.env(SCOOP_SCRIPT_ENV, scoop_script);
+            }
+            command.env("SCOOP", root);
+        }
+        if let Ok(global_root) = paths::global_root() {
+            command.env("SCOOP_GLOBAL", global_root);
+        }
+        command
+    }
+}
+
+impl fmt::Display for ScoopCommand {
+    /// 用于日志和操作历史,例如 `scoop install extras/vscode`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "scoop {}", self.args.join(" "))
+    }
+}
+
+/// 为操作历史描述一条命令:由 [`ScoopCommand`] 生成的命令还原出 scoop 参数
+pub(crate) fn describe(command: &Command) -> String {
+    let args = command
+        .get_envs()
+        .find(|(key, _)| *key == ARGS_ENV)
+        .and_then(|(_, value)| value)
+        .and_then(|value| serde_json::from_str::<Vec<String>>(&value.to_string_lossy()).ok());
+    match args {
+        Some(args) => format!("scoop {}", args.join(" ")),
+        None => std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+/// Scoop 的应用名和 bucket 名:字母、数字、`-`、`_`、`.`,并且以字母或数字开头
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 128
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// 应用名,可以带 `bucket/` 前缀
+pub(crate) fn validate_app(app: &str) -> Result<String, InvalidArgument> {
+    let valid = match app.split_once('/') {
+        Some((bucket, name)) => is_identifier(bucket) && is_identifier(name),
+        None => is_identifier(app),
+    };
+    if valid {
+        Ok(app.to_string())
+    } else {
+        Err(InvalidArgument::AppName(app.to_string()))
+    }
+}
+
+/// 版本号:字母、数字、`.`、`-`、`_`、`+`,并且以字母或数字开头
+pub(crate) fn validate_version(version: &str) -> Result<String, InvalidArgument> {
+    let valid = !version.is_empty()
+        && version.len() <= 128
+        && version.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && version.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'));
+    if valid {
+        Ok(version.to_string())
+    } else {
+        Err(InvalidArgument::Version(version.to_string()))
+    }
+}
+
+pub(crate) fn validate_bucket(name: &str) -> Result<String, InvalidArgument> {
+    if is_identifier(name) {
+        Ok(name.to_string())
+    } else {
+        Err(InvalidArgument::BucketName(name.to_string()))
+    }
+}
+
+/// bucket 仓库地址,只接受 git 能识别的远程地址
+fn validate_bucket_url(url: &str) -> Result<String, InvalidArgument> {
+    let has_scheme = ["https://", "http://", "ssh://", "git://", "git@"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme));
+    if has_scheme && url.len() <= 2048 && !url.chars().any(|c| c.is_control() || c.is_whitespace()) {
+        Ok(url.to_string())
+    } else {
+        Err(InvalidArgument::BucketUrl(url.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTILE: &[&str] = &[
+        "",
+        "7zip; Remove-Item -Recurse C:\\",
+        "7zip & calc",
+        "7zip | Out-File x",
+        "$(calc)",
+        "`calc`",
+        "'7zip'",
+        "\"7zip\"",
+        "7zip\ncalc",
+        "7zip\0",
+        "7zip calc",
+        "--global",
+        "-g",
+        ".hidden",
+        "..",
+        "../../evil",
+        "extras/../main/7zip",
+        "extras/",
+        "/7zip",
+        "a/b/c",
+        "7zip@$(calc)",
+        "名字",
+        "7zip\u{202e}",
+    ];
+
+    #[test]
+    fn accepts_scoop_names() {
+        for name in ["7zip", "git", "nodejs-lts", "python_3.12", "vcredist2022", "extras/vscode", "versions/python311"] {
+            assert!(ScoopCommand::install_with(name, None, &InstallOptions::default()).is_ok(), "{} should be accepted", name);
+        }
+        assert!(ScoopCommand::bucket_add("extras", None).is_ok());
+        assert!(ScoopCommand::bucket_add("my-bucket", Some("https://github.com/me/my-bucket")).is_ok());
+        assert!(ScoopCommand::bucket_add("private", Some("git@github.com:me/private.git")).is_ok());
+    }
+
+    #[test]
+    fn rejects_hostile_app_names() {
+        for name in HOSTILE {
+            assert_eq!(
+                ScoopCommand::install_with(name, None, &InstallOptions::default()),
+                Err(InvalidArgument::AppName(name.to_string())),
+                "{:?} should be rejected",
+                name
+            );
+            assert!(ScoopCommand::uninstall(name, Scope::User).is_err());
+            assert!(ScoopCommand::update(name, Scope::Global).is_err());
+        }
+        assert!(ScoopCommand::install_with(&"a".repeat(129), None, &InstallOptions::default()).is_err());
+    }
+
+    #[test]
+    fn installs_a_specific_version() {
+        let global = InstallOptions { global: true, ..Default::default() };
+        let command = ScoopCommand::install_with("extras/vscode", Some("1.84.0"), &global).unwrap();
+        assert_eq!(command.to_string(), "scoop install extras/vscode@1.84.0 --global");
+        let user = InstallOptions::default();
+        assert!(ScoopCommand::install_with("git", Some("2.43.0.windows.1"), &user).is_ok());
+        assert!(ScoopCommand::install_with("python", Some("3.13.0rc1+build"), &user).is_ok());
+
+        for version in HOSTILE {
+            assert_eq!(
+                ScoopCommand::install_with("7zip", Some(version), &user),
+                Err(InvalidArgument::Version(version.to_string())),
+                "{:?} should be rejected",
+                version
+            );
+        }
+        assert!(ScoopCommand::install_with("7zip; calc", Some("23.01"), &user).is_err());
+    }
+
+    #[test]
+    fn passes_install_options_as_flags() {
+        let options: InstallOptions = serde_json::from_str(
+            r#"{"arch": "32bit", "global": true, "no_cache": true, "skip_hash_check": true, "independent": true}"#,
+        ).unwrap();
+        let command = ScoopCommand::install_with("7zip", None, &options).unwrap();
+        assert_eq!(
+            command.args,
+            ["install", "7zip", "--global", "--arch", "32bit", "--no-cache", "--skip-hash-check", "--independent"]
+        );
+        assert_eq!(options.to_string(), "--global --arch 32bit --no-cache --skip-hash-check --independent");
+
+        let options: InstallOptions = serde_json::from_str(r#"{"arch": "arm64"}"#).unwrap();
+        assert_eq!(ScoopCommand::install_with("7zip", None, &options).unwrap().to_string(), "scoop install 7zip --arch arm64");
+        assert_eq!(InstallOptions::default().to_string(), "");
+        assert!(serde_json::from_str::<InstallOptions>(r#"{"arch": "x64; calc"}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_hostile_bucket_names_and_urls() {
+        for name in HOSTILE {
+            assert!(ScoopCommand::bucket_add(name, None).is_err(), "{:?} should be rejected", name);
+            assert!(ScoopCommand::bucket_rm(name).is_err());
+        }
+        assert!(ScoopCommand::bucket_add("extras/main", None).is_err());
+
+        for url in [
+            "",
+            "--help",
+            "C:\\buckets\\mine",
+            "https://example.com/a b",
+            "https://example.com/$(calc)\n",
+            "ext::sh -c calc",
+            "file:///C:/evil",
+        ] {
+            assert_eq!(
+                ScoopCommand::bucket_add("mine", Some(url)),
+                Err(InvalidArgument::BucketUrl(url.to_string())),
+                "{:?} should be rejected",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn arguments_are_passed_as_data() {
+        let command = ScoopCommand::install_with("extras/vscode", None, &InstallOptions::default()).unwrap();
+        assert_eq!(command.args, ["install", "extras/vscode"]);
+        assert_eq!(command.to_string(), "scoop install extras/vscode");
+
+        let process = command.to_command();
+        let script = process.get_args().last().unwrap().to_string_lossy().to_string();
+        assert_eq!(script, format!("{}{}", ARGV_PRELUDE, RUN_SCOOP));
+        assert!(!script.contains("vscode"));
+        assert_eq!(describe(&process), "scoop install extras/vscode");
+
+        let global = ScoopCommand::update("git", Scope::Global).unwrap();
+        assert_eq!(global.to_string(), "scoop update git --global");
+    }
+}
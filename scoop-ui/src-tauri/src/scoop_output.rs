@@ -0,0 +1,490 @@
+use serde::Serialize;
+
+/// 从 scoop install/update/uninstall 控制台输出中识别出的阶段
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScoopPhase {
+    /// `Installing '7zip' (23.01) [64bit] from 'main' bucket`
+    Installing {
+        app: String,
+        version: String,
+        architecture: Option<String>,
+        bucket: Option<String>,
+    },
+    /// `Updating 'git' (2.42.0 -> 2.43.0)`
+    Updating {
+        app: String,
+        from_version: String,
+        to_version: String,
+    },
+    /// `Downloading new version`、aria2 输出或 `Downloading <url> (1.8 MB)...`
+    ///
+    /// scoop 的输出被重定向时不显示下载进度条,只打印下载地址和文件大小,所以没有下载百分比。
+    Downloading {
+        file: Option<String>,
+        total_bytes: Option<u64>,
+    },
+    /// `Loading 7z2301-x64.msi from cache`
+    LoadingFromCache { file: String },
+    /// `Checking hash of 7z2301-x64.msi ... ok.`
+    CheckingHash { file: String },
+    /// `Extracting 7z2301-x64.msi ... done.`
+    Extracting { file: String },
+    /// `Running post_install script...done.`
+    RunningScript { script: String },
+    /// `Linking ~\scoop\apps\7zip\current => ~\scoop\apps\7zip\23.01`
+    Linking { file: String, target: Option<String> },
+    /// `Creating shim for '7z'.`
+    CreatingShim { name: String },
+    /// `Creating shortcut for 7-Zip (7zFM.exe)`
+    CreatingShortcut { name: String },
+    /// `Persisting Codecs`
+    Persisting { file: String },
+    /// `'7zip' (23.01) was installed successfully!`
+    Installed { app: String, version: String },
+    /// `git: 2.43.0 (latest version)`
+    AlreadyLatest { app: String, version: String },
+    /// `Uninstalling '7zip' (23.01).`
+    Uninstalling { app: String, version: String },
+    /// `Removing shim '7z.shim'.`
+    RemovingShim { name: String },
+    /// `Unlinking ~\scoop\apps\7zip\current`
+    Unlinking { file: String },
+    /// `'7zip' was uninstalled.`
+    Uninstalled { app: String },
+    /// `WARN  ...`
+    Warning { message: String },
+    /// `ERROR ...`
+    Error { message: String },
+}
+
+impl ScoopPhase {
+    /// 该阶段大致对应的整体进度 (0.0-1.0),无法判断时返回 None
+    pub fn progress_hint(&self) -> Option<f32> {
+        match self {
+            ScoopPhase::Updating { .. } => Some(0.02),
+            ScoopPhase::Installing { .. } => Some(0.05),
+            ScoopPhase::Downloading { .. } => Some(0.1),
+            ScoopPhase::LoadingFromCache { .. } => Some(0.5),
+            ScoopPhase::CheckingHash { .. } => Some(0.55),
+            ScoopPhase::Uninstalling { .. } => Some(0.1),
+            ScoopPhase::Extracting { .. } => Some(0.65),
+            ScoopPhase::RemovingShim { .. } => Some(0.5),
+            ScoopPhase::RunningScript { script } if script == "post_install" => Some(0.95),
+            ScoopPhase::RunningScript { .. } => Some(0.7),
+            ScoopPhase::Linking { .. } | ScoopPhase::Unlinking { .. } => Some(0.8),
+            ScoopPhase::CreatingShim { .. } => Some(0.85),
+            ScoopPhase::CreatingShortcut { .. } | ScoopPhase::Persisting { .. } => Some(0.9),
+            ScoopPhase::Installed { .. }
+            | ScoopPhase::AlreadyLatest { .. }
+            | ScoopPhase::Uninstalled { .. } => Some(1.0),
+            ScoopPhase::Warning { .. } | ScoopPhase::Error { .. } => None,
+        }
+    }
+}
+
+/// 把一条 scoop 命令输出的阶段换算成任务的整体进度,只增不减
+///
+/// `scoop update <app>` 先下载并校验新版本,再卸载旧版本、安装新版本:下载占前一半,
+/// 安装按 [`ScoopPhase::progress_hint`] 压缩到后一半,卸载旧版本时不会跳到安装的进度。
+/// 批量任务中每条命令占 `1/steps` 的区间,每条命令用一个新的 `ProgressTracker`。
+#[derive(Debug)]
+pub struct ProgressTracker {
+    step: usize,
+    steps: usize,
+    updating: bool,
+    reinstalling: bool,
+    progress: f32,
+}
+
+impl ProgressTracker {
+    /// 单独一条命令,进度为 0.0-1.0
+    pub fn new() -> Self {
+        Self::step(0, 1)
+    }
+
+    /// `steps` 条命令中的第 `step` 条(从 0 开始),进度落在 `step/steps` 到 `(step + 1)/steps` 之间
+    pub fn step(step: usize, steps: usize) -> Self {
+        Self {
+            step,
+            steps: steps.max(1),
+            updating: false,
+            reinstalling: false,
+            progress: 0.0,
+        }
+    }
+
+    /// 记录一行输出的阶段,返回任务的整体进度
+    pub fn advance(&mut self, phase: Option<&ScoopPhase>) -> f32 {
+        if let Some(hint) = phase.and_then(|phase| self.hint(phase)) {
+            self.progress = self.progress.max(hint);
+        }
+        (self.step as f32 + self.progress) / self.steps as f32
+    }
+
+    /// 本条命令中该阶段对应的进度
+    fn hint(&mut self, phase: &ScoopPhase) -> Option<f32> {
+        match phase {
+            ScoopPhase::Updating { .. } => self.updating = true,
+            ScoopPhase::Uninstalling { .. } if self.updating => self.reinstalling = true,
+            _ => {}
+        }
+        let hint = phase.progress_hint()?;
+        if !self.updating {
+            return Some(hint);
+        }
+        Some(match phase {
+            ScoopPhase::Updating { .. } => hint,
+            ScoopPhase::AlreadyLatest { .. } | ScoopPhase::Installed { .. } => 1.0,
+            // 卸载旧版本
+            ScoopPhase::Uninstalling { .. } => 0.45,
+            ScoopPhase::RemovingShim { .. } | ScoopPhase::Unlinking { .. } => 0.5,
+            ScoopPhase::RunningScript { script } if script.contains("uninstall") => 0.5,
+            // 安装新版本
+            _ if self.reinstalling => 0.5 + 0.5 * hint,
+            // 下载并校验新版本
+            _ => (hint * 0.8).min(0.45),
+        })
+    }
+}
+
+impl Default for ProgressTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 解析一行 scoop 输出,不认识的行返回 None
+pub fn parse_line(line: &str) -> Option<ScoopPhase> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    if let Some(message) = line.strip_prefix("WARN ") {
+        return Some(ScoopPhase::Warning { message: message.trim().to_string() });
+    }
+    if let Some(message) = line.strip_prefix("ERROR ") {
+        return Some(ScoopPhase::Error { message: message.trim().to_string() });
+    }
+
+    if let Some(rest) = line.strip_prefix("Installing ") {
+        let (app, rest) = quoted(rest)?;
+        let (version, rest) = parenthesized(rest)?;
+        let (architecture, rest) = match rest.trim_start().strip_prefix('[') {
+            Some(arch) => {
+                let end = arch.find(']')?;
+                (Some(arch[..end].to_string()), &arch[end + 1..])
+            }
+            None => (None, rest),
+        };
+        let bucket = rest
+            .trim()
+            .strip_prefix("from ")
+            .and_then(|source| source.strip_suffix(" bucket"))
+            .map(|bucket| bucket.trim_matches('\'').to_string());
+        return Some(ScoopPhase::Installing { app, version, architecture, bucket });
+    }
+
+    if let Some(rest) = line.strip_prefix("Updating ") {
+        // "Updating Scoop..." / "Updating Buckets..." 没有引号,不属于应用更新
+        let (app, rest) = quoted(rest)?;
+        let (versions, _) = parenthesized(rest)?;
+        let (from_version, to_version) = versions.split_once(" -> ")?;
+        return Some(ScoopPhase::Updating {
+            app,
+            from_version: from_version.trim().to_string(),
+            to_version: to_version.trim().to_string(),
+        });
+    }
+
+    // "Downloading https://www.7-zip.org/a/7z2301-x64.msi (1.8 MB)..."
+    if let Some((url, size)) = line
+        .strip_prefix("Downloading ")
+        .and_then(|rest| rest.strip_suffix(")..."))
+        .and_then(|rest| rest.rsplit_once(" ("))
+    {
+        return Some(ScoopPhase::Downloading { file: Some(url_file_name(url)), total_bytes: parse_size(size) });
+    }
+    if line.starts_with("Downloading") || line.starts_with("Starting download") {
+        return Some(ScoopPhase::Downloading { file: None, total_bytes: None });
+    }
+    if line.starts_with("Download: ") {
+        // aria2 输出
+        return Some(ScoopPhase::Downloading { file: None, total_bytes: None });
+    }
+
+    if let Some(rest) = line.strip_prefix("Loading ") {
+        let file = rest.strip_suffix(" from cache")?;
+        return Some(ScoopPhase::LoadingFromCache { file: file.to_string() });
+    }
+
+    if let Some(rest) = line.strip_prefix("Checking hash of ") {
+        return Some(ScoopPhase::CheckingHash { file: before_ellipsis(rest) });
+    }
+
+    if let Some(rest) = line.strip_prefix("Extracting ") {
+        return Some(ScoopPhase::Extracting { file: before_ellipsis(rest) });
+    }
+
+    if let Some(rest) = line.strip_prefix("Running ") {
+        let script = rest.split(|c: char| c.is_whitespace() || c == '.').next()?;
+        if script.is_empty() {
+            return None;
+        }
+        return Some(ScoopPhase::RunningScript { script: script.to_string() });
+    }
+
+    if let Some(rest) = line.strip_prefix("Linking ") {
+        return Some(match rest.split_once(" => ") {
+            Some((file, target)) => ScoopPhase::Linking {
+                file: file.trim().to_string(),
+                target: Some(target.trim().to_string()),
+            },
+            None => ScoopPhase::Linking { file: rest.trim().to_string(), target: None },
+        });
+    }
+
+    if let Some(rest) = line.strip_prefix("Unlinking ") {
+        return Some(ScoopPhase::Unlinking { file: rest.trim().to_string() });
+    }
+
+    if let Some(rest) = line.strip_prefix("Creating shim for ") {
+        let (name, _) = quoted(rest)?;
+        return Some(ScoopPhase::CreatingShim { name });
+    }
+
+    if let Some(rest) = line.strip_prefix("Creating shortcut for ") {
+        return Some(ScoopPhase::CreatingShortcut { name: rest.trim().to_string() });
+    }
+
+    if let Some(rest) = line.strip_prefix("Removing shim ") {
+        let (name, _) = quoted(rest)?;
+        return Some(ScoopPhase::RemovingShim { name });
+    }
+
+    if let Some(rest) = line.strip_prefix("Persisting ") {
+        return Some(ScoopPhase::Persisting { file: rest.trim().to_string() });
+    }
+
+    if let Some(rest) = line.strip_prefix("Uninstalling ") {
+        let (app, rest) = quoted(rest)?;
+        let (version, _) = parenthesized(rest)?;
+        return Some(ScoopPhase::Uninstalling { app, version });
+    }
+
+    if line.starts_with('\'') {
+        let (app, rest) = quoted(line)?;
+        if rest.trim() == "was uninstalled." {
+            return Some(ScoopPhase::Uninstalled { app });
+        }
+        let (version, rest) = parenthesized(rest)?;
+        if rest.trim().starts_with("was installed successfully") {
+            return Some(ScoopPhase::Installed { app, version });
+        }
+        return None;
+    }
+
+    if let Some(rest) = line.strip_suffix("(latest version)") {
+        let (app, version) = rest.split_once(": ")?;
+        return Some(ScoopPhase::AlreadyLatest {
+            app: app.trim().to_string(),
+            version: version.trim().to_string(),
+        });
+    }
+
+    None
+}
+
+/// 取出开头被单引号包裹的内容,返回 (内容, 剩余部分)
+fn quoted(s: &str) -> Option<(String, &str)> {
+    let s = s.trim_start().strip_prefix('\'')?;
+    let end = s.find('\'')?;
+    Some((s[..end].to_string(), &s[end + 1..]))
+}
+
+/// 取出开头被圆括号包裹的内容,返回 (内容, 剩余部分)
+fn parenthesized(s: &str) -> Option<(String, &str)> {
+    let s = s.trim_start().strip_prefix('(')?;
+    let end = s.find(')')?;
+    Some((s[..end].to_string(), &s[end + 1..]))
+}
+
+/// 下载地址对应的文件名,scoop 用 `#/name` 指定下载后的文件名
+fn url_file_name(url: &str) -> String {
+    match url.split_once("#/") {
+        Some((_, name)) => name.to_string(),
+        None => url.split(['?', '#']).next().unwrap_or(url).rsplit('/').next().unwrap_or(url).to_string(),
+    }
+}
+
+/// "1.8 MB" => 1887436,大的数字可能带千位分隔符
+fn parse_size(s: &str) -> Option<u64> {
+    let (number, unit) = s.trim().split_once(' ')?;
+    let number: f64 = number.replace(',', "").parse().ok()?;
+    let multiplier = match unit {
+        "B" => 1.0,
+        "KB" => 1024.0,
+        "MB" => 1024.0 * 1024.0,
+        "GB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * multiplier) as u64)
+}
+
+/// "7z2301-x64.msi ... ok." => "7z2301-x64.msi"
+fn before_ellipsis(s: &str) -> String {
+    s.split(" ...").next().unwrap_or(s).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phases(fixture: &str) -> Vec<ScoopPhase> {
+        fixture.lines().filter_map(parse_line).collect()
+    }
+
+    #[test]
+    fn parses_install_fixture() {
+        let phases = phases(include_str!("../tests/fixtures/install_7zip.txt"));
+        assert_eq!(
+            phases.first(),
+            Some(&ScoopPhase::Installing {
+                app: "7zip".to_string(),
+                version: "23.01".to_string(),
+                architecture: Some("64bit".to_string()),
+                bucket: Some("main".to_string()),
+            })
+        );
+        assert!(phases.contains(&ScoopPhase::Downloading {
+            file: Some("7z2301-x64.msi".to_string()),
+            total_bytes: Some(1887436),
+        }));
+        assert!(phases.contains(&ScoopPhase::CheckingHash { file: "7z2301-x64.msi".to_string() }));
+        assert!(phases.contains(&ScoopPhase::Extracting { file: "7z2301-x64.msi".to_string() }));
+        assert!(phases.contains(&ScoopPhase::Linking {
+            file: "~\\scoop\\apps\\7zip\\current".to_string(),
+            target: Some("~\\scoop\\apps\\7zip\\23.01".to_string()),
+        }));
+        assert!(phases.contains(&ScoopPhase::CreatingShim { name: "7z".to_string() }));
+        assert!(phases.contains(&ScoopPhase::CreatingShortcut { name: "7-Zip (7zFM.exe)".to_string() }));
+        assert!(phases.contains(&ScoopPhase::RunningScript { script: "post_install".to_string() }));
+        assert_eq!(
+            phases.last(),
+            Some(&ScoopPhase::Installed { app: "7zip".to_string(), version: "23.01".to_string() })
+        );
+    }
+
+    #[test]
+    fn parses_cached_install_fixture() {
+        let phases = phases(include_str!("../tests/fixtures/install_cached_nodejs.txt"));
+        assert!(phases.contains(&ScoopPhase::LoadingFromCache {
+            file: "node-v20.10.0-win-x64.7z".to_string()
+        }));
+        assert!(phases.contains(&ScoopPhase::Persisting { file: "bin".to_string() }));
+        assert!(phases.iter().all(|p| !matches!(p, ScoopPhase::Downloading { .. })));
+    }
+
+    #[test]
+    fn parses_update_fixture() {
+        let phases = phases(include_str!("../tests/fixtures/update_git.txt"));
+        assert_eq!(
+            phases.first(),
+            Some(&ScoopPhase::Updating {
+                app: "git".to_string(),
+                from_version: "2.42.0.2".to_string(),
+                to_version: "2.43.0".to_string(),
+            })
+        );
+        assert!(phases.contains(&ScoopPhase::Uninstalling {
+            app: "git".to_string(),
+            version: "2.42.0.2".to_string(),
+        }));
+        assert!(phases.contains(&ScoopPhase::Installed {
+            app: "git".to_string(),
+            version: "2.43.0".to_string(),
+        }));
+        assert!(phases.contains(&ScoopPhase::AlreadyLatest {
+            app: "7zip".to_string(),
+            version: "23.01".to_string(),
+        }));
+    }
+
+    #[test]
+    fn parses_uninstall_fixture() {
+        let phases = phases(include_str!("../tests/fixtures/uninstall_7zip.txt"));
+        assert_eq!(
+            phases,
+            vec![
+                ScoopPhase::Uninstalling { app: "7zip".to_string(), version: "23.01".to_string() },
+                ScoopPhase::RemovingShim { name: "7z.shim".to_string() },
+                ScoopPhase::RemovingShim { name: "7z.exe".to_string() },
+                ScoopPhase::Unlinking { file: "~\\scoop\\apps\\7zip\\current".to_string() },
+                ScoopPhase::Uninstalled { app: "7zip".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_warnings_and_errors() {
+        assert_eq!(
+            parse_line("WARN  '7zip' (23.01) is already installed."),
+            Some(ScoopPhase::Warning { message: "'7zip' (23.01) is already installed.".to_string() })
+        );
+        assert_eq!(
+            parse_line("ERROR Hash check failed!"),
+            Some(ScoopPhase::Error { message: "Hash check failed!".to_string() })
+        );
+        assert_eq!(parse_line("Updating Scoop..."), None);
+        assert_eq!(parse_line("Notes"), None);
+    }
+
+    /// 按 fixture 的每一行推进进度,返回每一行之后的进度
+    fn progress(fixture: &str, mut tracker: ProgressTracker) -> Vec<f32> {
+        fixture.lines().map(|line| tracker.advance(parse_line(line).as_ref())).collect()
+    }
+
+    #[test]
+    fn update_progress_covers_download_then_reinstall() {
+        let fixture = include_str!("../tests/fixtures/update_git.txt");
+        let lines: Vec<&str> = fixture.lines().collect();
+        let progress = progress(fixture, ProgressTracker::new());
+        let at = |prefix: &str| progress[lines.iter().position(|line| line.starts_with(prefix)).unwrap()];
+
+        assert!(progress.windows(2).all(|w| w[0] <= w[1]));
+        // 卸载旧版本时不会超过之后安装新版本的阶段
+        assert!(at("Checking hash") < at("Uninstalling"));
+        assert!(at("Uninstalling") < at("Extracting"));
+        assert!(at("Extracting") < at("Linking"));
+        assert!(at("Creating shim") < at("'git' (2.43.0) was installed"));
+        assert_eq!(at("'git' (2.43.0) was installed"), 1.0);
+    }
+
+    #[test]
+    fn steps_share_the_overall_progress() {
+        let fixture = include_str!("../tests/fixtures/install_7zip.txt");
+        let first = progress(fixture, ProgressTracker::step(0, 4));
+        let second = progress(fixture, ProgressTracker::step(1, 4));
+        assert_eq!(first.first(), Some(&(0.05 / 4.0)));
+        assert_eq!(first.last(), Some(&0.25));
+        assert!(second.iter().all(|p| (0.25..=0.5).contains(p)));
+        assert_eq!(second.last(), Some(&0.5));
+
+        let mut tracker = ProgressTracker::step(3, 4);
+        assert_eq!(tracker.advance(None), 0.75);
+    }
+
+    #[test]
+    fn parses_download_size() {
+        assert_eq!(
+            parse_line("Downloading https://example.com/releases/v1.0/tool.zip?raw=true (1,024.0 MB)..."),
+            Some(ScoopPhase::Downloading { file: Some("tool.zip".to_string()), total_bytes: Some(1073741824) })
+        );
+        assert_eq!(
+            parse_line("Downloading https://example.com/download.php?id=4#/dl.7z (512 B)..."),
+            Some(ScoopPhase::Downloading { file: Some("dl.7z".to_string()), total_bytes: Some(512) })
+        );
+        assert_eq!(parse_line("Downloading new version"), Some(ScoopPhase::Downloading { file: None, total_bytes: None }));
+    }
+}
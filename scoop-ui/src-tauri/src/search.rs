@@ -0,0 +1,586 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::manifest_index::IndexEntry;
+use crate::scoop_command::InvalidArgument;
+use crate::versions;
+
+/// 名字完全相同
+const NAME_EXACT: u32 = 1000;
+/// 名字以查询词开头
+const NAME_PREFIX: u32 = 600;
+/// 名字中的某个单词(以 `-`、`_`、`.` 分隔)以查询词开头
+const NAME_WORD: u32 = 400;
+const NAME_SUBSTRING: u32 = 300;
+const BIN_EXACT: u32 = 350;
+const BIN_PREFIX: u32 = 200;
+const BIN_SUBSTRING: u32 = 100;
+/// 主页域名中的一段与查询词相同,例如 `git` 对应 `gitforwindows.org` 不算,`7-zip.org` 中的 `7-zip` 算
+const DOMAIN_LABEL: u32 = 150;
+const DOMAIN_SUBSTRING: u32 = 60;
+const DESCRIPTION_WORD: u32 = 120;
+const DESCRIPTION_WORD_PREFIX: u32 = 80;
+const DESCRIPTION_SUBSTRING: u32 = 40;
+/// 名字与查询词只差几处拼写错误,每处错误再扣 [`FUZZY_PENALTY`]
+const NAME_FUZZY: u32 = 250;
+const FUZZY_PENALTY: u32 = 75;
+/// 没有精确命中时最多给出的"你是不是要找"建议数
+const MAX_SUGGESTIONS: usize = 3;
+
+/// 命中的字段
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchField {
+    Name,
+    Description,
+    Homepage,
+    Bin,
+}
+
+/// 字段中命中的一段,`start`/`end` 为 UTF-16 偏移,可以直接用于 JavaScript 字符串
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Highlight {
+    pub field: MatchField,
+    /// 命中的是第几个 bin,只用于 [`MatchField::Bin`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin: Option<usize>,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// 一条搜索结果
+#[derive(Debug)]
+pub struct SearchHit<'a> {
+    pub entry: &'a IndexEntry,
+    pub score: u32,
+    pub highlights: Vec<Highlight>,
+    /// 至少有一个词只是模糊匹配到名字
+    pub fuzzy: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SearchOptions {
+    /// 跳过排在前面的多少条,用于分页
+    pub offset: usize,
+    pub limit: usize,
+    /// 模糊匹配允许的最多拼写错误数(增删改一个字符或交换相邻两个字符各算一处),0 表示关闭模糊匹配
+    ///
+    /// 实际允许的错误数还受词长限制:3 个字符以内不允许,4~5 个字符最多 1 处。
+    pub max_typos: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self { offset: 0, limit: 50, max_typos: 2 }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SearchOutcome<'a> {
+    /// `offset` 开始的至多 `limit` 条结果
+    pub hits: Vec<SearchHit<'a>>,
+    /// 分页前的结果总数
+    pub total: usize,
+    /// 没有任何精确命中时,模糊匹配到的应用名,按相关度排列
+    pub suggestions: Vec<String>,
+}
+
+/// 搜索的过滤条件,没有设置的条件不参与过滤
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Facets {
+    /// 只保留这些 bucket 中的应用
+    pub buckets: Vec<String>,
+    /// 只保留使用这些许可证的应用(SPDX 标识,不区分大小写)
+    pub licenses: Vec<String>,
+    /// 只保留支持该架构(`64bit`/`32bit`/`arm64`)的应用,没有 `architecture` 的应用视为都支持
+    pub architecture: Option<String>,
+    pub installed: Option<bool>,
+    pub has_update: Option<bool>,
+}
+
+impl Facets {
+    /// `installed` 为已安装应用的小写名字 -> 已安装版本
+    pub fn matches(&self, entry: &IndexEntry, installed: &HashMap<String, String>) -> bool {
+        let installed_version = installed_version(entry, installed);
+        (self.buckets.is_empty() || self.buckets.iter().any(|bucket| bucket.eq_ignore_ascii_case(&entry.bucket)))
+            && (self.licenses.is_empty()
+                || entry.license.as_ref().is_some_and(|license| {
+                    self.licenses.iter().any(|wanted| wanted.eq_ignore_ascii_case(license))
+                }))
+            && self.architecture.as_ref().is_none_or(|arch| {
+                entry.architectures.is_empty() || entry.architectures.contains(arch)
+            })
+            && self.installed.is_none_or(|wanted| wanted == installed_version.is_some())
+            && self.has_update.is_none_or(|wanted| wanted == has_update(entry, installed_version))
+    }
+}
+
+/// 条目对应应用的已安装版本,没有安装时为 None
+pub fn installed_version<'a>(entry: &IndexEntry, installed: &'a HashMap<String, String>) -> Option<&'a str> {
+    installed.get(&entry.name.to_lowercase()).map(String::as_str)
+}
+
+/// bucket 中的版本比已安装的版本新
+pub fn has_update(entry: &IndexEntry, installed_version: Option<&str>) -> bool {
+    installed_version.is_some_and(|version| versions::compare(&entry.version, version).is_gt())
+}
+
+/// 各自计算代次的搜索命令,一种搜索的新请求不会取代另一种搜索
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchChannel {
+    /// `search_apps`:所有 bucket,包括还没有添加的推荐 bucket
+    AllBuckets,
+    /// `search_local_packets`:本地索引的分面搜索
+    Local,
+}
+
+/// 搜索的代次
+///
+/// 界面每次搜索带上递增的请求 id,快速输入时先发出的搜索可能后完成,
+/// 同一 [`SearchChannel`] 中已经有更新的搜索开始时,旧的搜索不再返回结果。
+#[derive(Debug, Default)]
+pub struct Generations {
+    latest: [AtomicU64; 2],
+}
+
+impl Generations {
+    /// 登记编号为 `id` 的搜索,同一种搜索已经有更新的请求开始时返回 false
+    pub fn begin(&self, channel: SearchChannel, id: u64) -> bool {
+        self.latest[channel as usize].fetch_max(id, Ordering::SeqCst) <= id
+    }
+
+    /// 编号为 `id` 的搜索是否已被同一种搜索的更新请求取代
+    pub fn is_superseded(&self, channel: SearchChannel, id: u64) -> bool {
+        self.latest[channel as usize].load(Ordering::SeqCst) > id
+    }
+}
+
+/// 搜索的查询词。查询只在索引中匹配,不会传给 scoop,所以只限制长度并拒绝控制字符
+pub fn validate_query(query: &str) -> Result<String, InvalidArgument> {
+    if query.len() > 256 || query.chars().any(char::is_control) {
+        return Err(InvalidArgument::Query(query.to_string()));
+    }
+    Ok(query.to_string())
+}
+
+/// 在索引中搜索,按相关度从高到低返回 `offset` 开始的至多 `limit` 条
+///
+/// 查询按空白拆分为多个词,每个词都必须在名字、描述、主页域名或 bin 中出现(不区分大小写),
+/// 都没有出现时允许与名字模糊匹配,但只要有精确命中的结果就不返回模糊匹配的结果;
+/// 得分为每个词最佳命中的得分之和。得分相同时名字短的在前。
+///
+/// 查询为空时按名字列出所有条目,用于配合 [`Facets`] 浏览整个 bucket。
+pub fn search<'a>(
+    entries: impl IntoIterator<Item = &'a IndexEntry>,
+    query: &str,
+    options: &SearchOptions,
+) -> SearchOutcome<'a> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    if terms.is_empty() {
+        let mut entries: Vec<&IndexEntry> = entries.into_iter().collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.bucket.cmp(&b.bucket)));
+        let total = entries.len();
+        let hits = entries.into_iter()
+            .skip(options.offset)
+            .take(options.limit)
+            .map(|entry| SearchHit { entry, score: 0, highlights: Vec::new(), fuzzy: false })
+            .collect();
+        return SearchOutcome { hits, total, suggestions: Vec::new() };
+    }
+
+    let mut hits: Vec<SearchHit> = entries.into_iter()
+        .filter_map(|entry| score_entry(entry, &terms, options.max_typos))
+        .collect();
+    // 模糊匹配只在没有精确命中时作为退路
+    if hits.iter().any(|hit| !hit.fuzzy) {
+        hits.retain(|hit| !hit.fuzzy);
+    }
+    hits.sort_by(|a, b| {
+        b.score.cmp(&a.score)
+            .then(a.entry.name.len().cmp(&b.entry.name.len()))
+            .then_with(|| a.entry.name.cmp(&b.entry.name))
+            .then_with(|| a.entry.bucket.cmp(&b.entry.bucket))
+    });
+
+    let mut suggestions: Vec<String> = Vec::new();
+    if hits.first().is_some_and(|hit| hit.fuzzy) {
+        for hit in &hits {
+            if suggestions.len() < MAX_SUGGESTIONS && !suggestions.contains(&hit.entry.name) {
+                suggestions.push(hit.entry.name.clone());
+            }
+        }
+    }
+    let total = hits.len();
+    let hits = hits.into_iter().skip(options.offset).take(options.limit).collect();
+    SearchOutcome { hits, total, suggestions }
+}
+
+fn score_entry<'a>(entry: &'a IndexEntry, terms: &[String], max_typos: usize) -> Option<SearchHit<'a>> {
+    let name = entry.name.to_lowercase();
+    let description = entry.description.to_lowercase();
+    let homepage = entry.homepage.to_lowercase();
+    let bins: Vec<String> = entry.bins.iter().map(|bin| bin.to_lowercase()).collect();
+    let domain = domain_range(&homepage);
+
+    let mut score = 0;
+    let mut highlights = Vec::new();
+    let mut fuzzy = false;
+    for term in terms {
+        let mut best = 0;
+
+        if let Some(start) = name.find(term.as_str()) {
+            best = best.max(if name == *term {
+                NAME_EXACT
+            } else if start == 0 {
+                NAME_PREFIX
+            } else if find_word(&name, term, false).is_some() {
+                NAME_WORD
+            } else {
+                NAME_SUBSTRING
+            });
+            highlights.push(highlight(&entry.name, MatchField::Name, None, start, term.len()));
+        }
+
+        for (i, bin) in bins.iter().enumerate() {
+            if let Some(start) = bin.find(term.as_str()) {
+                best = best.max(if bin == term {
+                    BIN_EXACT
+                } else if start == 0 {
+                    BIN_PREFIX
+                } else {
+                    BIN_SUBSTRING
+                });
+                highlights.push(highlight(&entry.bins[i], MatchField::Bin, Some(i), start, term.len()));
+            }
+        }
+
+        if let Some((host_start, host_end)) = domain {
+            let host = &homepage[host_start..host_end];
+            if let Some(start) = host.find(term.as_str()) {
+                best = best.max(if host.split('.').any(|label| label == term) { DOMAIN_LABEL } else { DOMAIN_SUBSTRING });
+                highlights.push(highlight(&entry.homepage, MatchField::Homepage, None, host_start + start, term.len()));
+            }
+        }
+
+        if let Some(start) = description.find(term.as_str()) {
+            let (start, points) = if let Some(start) = find_word(&description, term, true) {
+                (start, DESCRIPTION_WORD)
+            } else if let Some(start) = find_word(&description, term, false) {
+                (start, DESCRIPTION_WORD_PREFIX)
+            } else {
+                (start, DESCRIPTION_SUBSTRING)
+            };
+            best = best.max(points);
+            highlights.push(highlight(&entry.description, MatchField::Description, None, start, term.len()));
+        }
+
+        if best == 0 {
+            if let Some(typos) = fuzzy_distance(&name, term, max_typos) {
+                best = NAME_FUZZY - FUZZY_PENALTY * typos as u32;
+                fuzzy = true;
+            }
+        }
+
+        // 每个词都必须命中
+        if best == 0 {
+            return None;
+        }
+        score += best;
+    }
+
+    Some(SearchHit {
+        entry,
+        score,
+        highlights: merge(highlights),
+        fuzzy,
+    })
+}
+
+/// 查询词与名字的拼写错误数,超过允许的错误数时返回 None
+///
+/// 分别与整个名字、名字中的各个单词和名字等长的前缀比较,取最小值,
+/// 这样 `pyhton` 能匹配 `python`,`nodjs` 能匹配 `nodejs-lts`。
+fn fuzzy_distance(name: &str, term: &str, max_typos: usize) -> Option<usize> {
+    let term_len = term.chars().count();
+    let allowed = max_typos.min(match term_len {
+        0..=3 => 0,
+        4..=5 => 1,
+        _ => 2,
+    });
+    if allowed == 0 {
+        return None;
+    }
+
+    let prefix: String = name.chars().take(term_len).collect();
+    std::iter::once(name)
+        .chain(name.split(['-', '_', '.']))
+        .chain(std::iter::once(prefix.as_str()))
+        .filter_map(|candidate| edit_distance(candidate, term, allowed))
+        .min()
+}
+
+/// 带相邻交换的编辑距离(optimal string alignment),超过 `limit` 时提前返回 None
+fn edit_distance(a: &str, b: &str, limit: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > limit {
+        return None;
+    }
+
+    let mut before_previous: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_previous[j - 2] + 1);
+            }
+        }
+        if current.iter().min().is_some_and(|&min| min > limit) {
+            return None;
+        }
+        before_previous = std::mem::replace(&mut previous, current);
+    }
+    Some(previous[b.len()]).filter(|&distance| distance <= limit)
+}
+
+/// 找到以单词开头处出现的 `term`;`whole` 为 true 时还要求在单词结尾处结束
+fn find_word(text: &str, term: &str, whole: bool) -> Option<usize> {
+    let is_word = |c: char| c.is_alphanumeric();
+    text.match_indices(term).map(|(start, _)| start).find(|&start| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + term.len()..].chars().next();
+        before.is_none_or(|c| !is_word(c)) && (!whole || after.is_none_or(|c| !is_word(c)))
+    })
+}
+
+/// 主页中主机名所在的字节范围,去掉协议、`www.` 和端口
+fn domain_range(homepage: &str) -> Option<(usize, usize)> {
+    let start = homepage.find("://").map_or(0, |i| i + 3);
+    let rest = &homepage[start..];
+    let start = start + if rest.starts_with("www.") { 4 } else { 0 };
+    let end = homepage[start..].find(['/', ':', '?', '#']).map_or(homepage.len(), |i| start + i);
+    (end > start).then_some((start, end))
+}
+
+/// 由小写文本中的字节偏移构造高亮
+///
+/// 小写化可能改变非 ASCII 字符的字节长度,所以先按字符数换算回原文,再转换为 UTF-16 偏移。
+fn highlight(original: &str, field: MatchField, bin: Option<usize>, start: usize, len: usize) -> Highlight {
+    let lower = original.to_lowercase();
+    let start_chars = lower[..start].chars().count();
+    let len_chars = lower[start..start + len].chars().count();
+    let utf16 = |chars: usize| original.chars().take(chars).map(char::len_utf16).sum::<usize>();
+    Highlight {
+        field,
+        bin,
+        start: utf16(start_chars),
+        end: utf16(start_chars + len_chars),
+    }
+}
+
+/// 合并同一字段中重叠或相邻的高亮,并按位置排序
+fn merge(mut highlights: Vec<Highlight>) -> Vec<Highlight> {
+    let field_order = |field: MatchField| field as u8;
+    highlights.sort_by_key(|h| (field_order(h.field), h.bin, h.start));
+    let mut merged: Vec<Highlight> = Vec::new();
+    for h in highlights {
+        match merged.last_mut() {
+            Some(last) if last.field == h.field && last.bin == h.bin && h.start <= last.end => {
+                last.end = last.end.max(h.end);
+            }
+            _ => merged.push(h),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, description: &str, homepage: &str, bins: &[&str]) -> IndexEntry {
+        IndexEntry {
+            name: name.to_string(),
+            bucket: "main".to_string(),
+            version: "1.0".to_string(),
+            description: description.to_string(),
+            homepage: homepage.to_string(),
+            license: None,
+            bins: bins.iter().map(|bin| bin.to_string()).collect(),
+            binaries: Vec::new(),
+            architectures: Vec::new(),
+            mtime: 0,
+        }
+    }
+
+    fn catalogue() -> Vec<IndexEntry> {
+        vec![
+            entry("git", "Distributed version control system", "https://gitforwindows.org", &["git", "git-bash"]),
+            entry("git-lfs", "Git extension for versioning large files", "https://git-lfs.github.com", &["git-lfs"]),
+            entry("lazygit", "Simple terminal UI for git commands", "https://github.com/jesseduffield/lazygit", &["lazygit"]),
+            entry("gitui", "Blazing fast terminal-ui for git", "https://github.com/extrawurst/gitui", &["gitui"]),
+            entry("7zip", "A multi-format file archiver", "https://www.7-zip.org/", &["7z", "7zFM"]),
+            entry("vscode", "Lightweight but powerful source code editor", "https://code.visualstudio.com/", &["code"]),
+            entry("neovim", "Vim-fork focused on extensibility", "https://neovim.io/", &["nvim", "nvim-qt"]),
+            entry("python", "A programming language", "https://www.python.org/", &["python", "python3"]),
+            entry("nodejs-lts", "JavaScript runtime (Long Term Support)", "https://nodejs.org", &["node"]),
+        ]
+    }
+
+    fn names(hits: &[SearchHit]) -> Vec<String> {
+        hits.iter().map(|hit| hit.entry.name.clone()).collect()
+    }
+
+    #[test]
+    fn ranks_exact_then_prefix_then_word_matches() {
+        let entries = catalogue();
+        let hits = search(&entries, "git", &SearchOptions::default()).hits;
+        assert_eq!(names(&hits), ["git", "gitui", "git-lfs", "lazygit"]);
+        assert!(hits[0].score > hits[1].score);
+        assert_eq!(hits[2].score, hits[1].score);
+    }
+
+    #[test]
+    fn matches_descriptions_domains_and_bins() {
+        let entries = catalogue();
+        assert_eq!(names(&search(&entries, "editor", &SearchOptions::default()).hits), ["vscode"]);
+        assert_eq!(names(&search(&entries, "7-zip", &SearchOptions::default()).hits), ["7zip"]);
+        assert_eq!(names(&search(&entries, "nvim", &SearchOptions::default()).hits), ["neovim"]);
+        assert_eq!(names(&search(&entries, "code", &SearchOptions::default()).hits), ["vscode"]);
+        assert_eq!(names(&search(&entries, "terminal git", &SearchOptions::default()).hits), ["gitui", "lazygit"]);
+        assert!(search(&entries, "terminal nothing", &SearchOptions::default()).hits.is_empty());
+        assert_eq!(search(&entries, "git", &SearchOptions { limit: 2, ..Default::default() }).hits.len(), 2);
+    }
+
+    #[test]
+    fn returns_utf16_highlights() {
+        let entries = catalogue();
+        let hits = search(&entries, "vim", &SearchOptions::default()).hits;
+        let neovim = hits.iter().find(|hit| hit.entry.name == "neovim").unwrap();
+        assert_eq!(neovim.highlights, [
+            Highlight { field: MatchField::Name, bin: None, start: 3, end: 6 },
+            Highlight { field: MatchField::Description, bin: None, start: 0, end: 3 },
+            Highlight { field: MatchField::Homepage, bin: None, start: 11, end: 14 },
+            Highlight { field: MatchField::Bin, bin: Some(0), start: 1, end: 4 },
+            Highlight { field: MatchField::Bin, bin: Some(1), start: 1, end: 4 },
+        ]);
+
+        let hits = search(&entries, "git bash", &SearchOptions::default()).hits;
+        assert_eq!(hits[0].highlights.iter().filter(|h| h.field == MatchField::Bin).collect::<Vec<_>>(), [
+            &Highlight { field: MatchField::Bin, bin: Some(0), start: 0, end: 3 },
+            &Highlight { field: MatchField::Bin, bin: Some(1), start: 0, end: 3 },
+            &Highlight { field: MatchField::Bin, bin: Some(1), start: 4, end: 8 },
+        ]);
+
+        let chinese = [entry("qq", "腾讯 QQ 即时通讯", "https://im.qq.com", &[])];
+        let hits = search(&chinese, "qq", &SearchOptions::default()).hits;
+        let description = hits[0].highlights.iter().find(|h| h.field == MatchField::Description).unwrap();
+        assert_eq!((description.start, description.end), (3, 5));
+    }
+
+    #[test]
+    fn tolerates_typos_in_names() {
+        let entries = catalogue();
+        let outcome = search(&entries, "pyhton", &SearchOptions::default());
+        assert_eq!(names(&outcome.hits), ["python"]);
+        assert!(outcome.hits[0].fuzzy);
+        assert_eq!(outcome.suggestions, ["python"]);
+
+        assert_eq!(names(&search(&entries, "vscdoe", &SearchOptions::default()).hits), ["vscode"]);
+        assert_eq!(names(&search(&entries, "nodjs", &SearchOptions::default()).hits), ["nodejs-lts"]);
+        // 太短的词不做模糊匹配
+        assert!(search(&entries, "gti", &SearchOptions::default()).hits.is_empty());
+        assert!(search(&entries, "pyhton", &SearchOptions { max_typos: 0, ..Default::default() }).hits.is_empty());
+        assert!(search(&entries, "pythonnnn", &SearchOptions::default()).hits.is_empty());
+    }
+
+    #[test]
+    fn suggests_only_without_exact_hits() {
+        let entries = catalogue();
+        let outcome = search(&entries, "python", &SearchOptions::default());
+        assert!(!outcome.hits[0].fuzzy);
+        assert!(outcome.suggestions.is_empty());
+        assert!(search(&entries, "zzzzzz", &SearchOptions::default()).suggestions.is_empty());
+    }
+
+    #[test]
+    fn pages_results_and_lists_everything_without_a_query() {
+        let entries = catalogue();
+        let outcome = search(&entries, "git", &SearchOptions { offset: 1, limit: 2, ..Default::default() });
+        assert_eq!(outcome.total, 4);
+        assert_eq!(names(&outcome.hits), ["gitui", "git-lfs"]);
+        assert!(search(&entries, "git", &SearchOptions { offset: 10, ..Default::default() }).hits.is_empty());
+
+        // 查询为空时按名字列出
+        let outcome = search(&entries, "  ", &SearchOptions { limit: 3, ..Default::default() });
+        assert_eq!(outcome.total, entries.len());
+        assert_eq!(names(&outcome.hits), ["7zip", "git", "git-lfs"]);
+    }
+
+    #[test]
+    fn filters_by_facets() {
+        let mut entries = catalogue();
+        entries[4].bucket = "extras".to_string();
+        entries[4].license = Some("LGPL-2.1-or-later".to_string());
+        entries[5].architectures = vec!["64bit".to_string(), "arm64".to_string()];
+        let installed = HashMap::from([
+            ("git".to_string(), "1.0".to_string()),
+            ("vscode".to_string(), "0.9".to_string()),
+        ]);
+        let filter = |facets: Facets| -> Vec<String> {
+            entries.iter().filter(|entry| facets.matches(entry, &installed)).map(|entry| entry.name.clone()).collect()
+        };
+
+        assert_eq!(filter(Facets { buckets: vec!["Extras".to_string()], ..Default::default() }), ["7zip"]);
+        assert_eq!(filter(Facets { licenses: vec!["lgpl-2.1-or-later".to_string()], ..Default::default() }), ["7zip"]);
+        assert_eq!(filter(Facets { installed: Some(true), ..Default::default() }), ["git", "vscode"]);
+        assert_eq!(filter(Facets { has_update: Some(true), ..Default::default() }), ["vscode"]);
+        assert_eq!(filter(Facets { installed: Some(true), has_update: Some(false), ..Default::default() }), ["git"]);
+        // 没有 architecture 的应用视为支持所有架构
+        let x86 = filter(Facets { architecture: Some("32bit".to_string()), ..Default::default() });
+        assert_eq!(x86.len(), entries.len() - 1);
+        assert!(!x86.contains(&"vscode".to_string()));
+    }
+
+    #[test]
+    fn newer_searches_supersede_older_ones() {
+        let generations = Generations::default();
+        let local = SearchChannel::Local;
+        assert!(generations.begin(local, 1));
+        assert!(generations.begin(local, 3));
+        assert!(generations.is_superseded(local, 1));
+        assert!(!generations.is_superseded(local, 3));
+        // 晚到的旧请求直接作废
+        assert!(!generations.begin(local, 2));
+        assert!(generations.begin(local, 3));
+
+        // 另一种搜索各自计数
+        assert!(generations.begin(SearchChannel::AllBuckets, 2));
+        assert!(!generations.is_superseded(SearchChannel::AllBuckets, 2));
+        assert!(generations.begin(SearchChannel::AllBuckets, 5));
+        assert!(!generations.is_superseded(local, 3));
+    }
+
+    #[test]
+    fn validates_queries() {
+        assert!(validate_query("node js").is_ok());
+        assert!(validate_query("-g").is_ok());
+        for query in ["node\ncalc", "node\0", &"a".repeat(257)] {
+            assert!(validate_query(query).is_err(), "{:?} should be rejected", query);
+        }
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(edit_distance("python", "pyhton", 2), Some(1));
+        assert_eq!(edit_distance("vscode", "vscod", 2), Some(1));
+        assert_eq!(edit_distance("kitten", "sitting", 3), Some(3));
+        assert_eq!(edit_distance("kitten", "sitting", 2), None);
+        assert_eq!(edit_distance("abc", "abc", 0), Some(0));
+    }
+}
@@ -0,0 +1,228 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::manifest::{InstallInfo, Manifest};
+use crate::manifest_index::IndexEntry;
+use crate::paths::Scope;
+use crate::versions;
+
+/// 一个已安装应用的状态,对应 `scoop status` 表格中的一行
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct AppStatus {
+    pub name: String,
+    pub scope: Scope,
+    /// 来源 bucket,从 URL 或本地文件安装时没有
+    pub bucket: Option<String>,
+    /// 已安装的版本,安装失败时为空
+    pub installed: String,
+    /// bucket 中的版本,manifest 已删除或不是从 bucket 安装时没有
+    pub latest: Option<String>,
+    /// bucket 中的版本比已安装的版本新
+    pub outdated: bool,
+    /// 已用 `scoop hold` 锁定版本,`scoop update` 会跳过
+    pub held: bool,
+    /// 来源 bucket 已删除,或其中已经没有这个应用
+    pub removed: bool,
+    /// manifest 已被移到 bucket 的 `deprecated` 目录
+    pub deprecated: bool,
+    /// 已安装 manifest 的 `depends` 中还没有安装的应用
+    pub missing_dependencies: Vec<String>,
+    /// 安装没有完成:`current` 中缺少 `install.json` 或 `manifest.json`
+    pub failed: bool,
+}
+
+impl AppStatus {
+    /// `scoop status` 会列出的应用:可以更新,或者有需要处理的问题
+    pub fn needs_attention(&self) -> bool {
+        self.outdated || self.removed || self.deprecated || self.failed || !self.missing_dependencies.is_empty()
+    }
+
+    /// 执行 `scoop update` 时会被更新
+    pub fn is_updatable(&self) -> bool {
+        self.outdated && !self.held && !self.failed
+    }
+}
+
+/// 批量更新时要处理的应用
+#[derive(Debug, Default, PartialEq)]
+pub struct BulkUpdatePlan {
+    /// 可以更新的应用,逐个执行 `scoop update <app>`
+    pub update: Vec<String>,
+    /// 已锁定的应用,不论有没有新版本都跳过
+    pub held: Vec<String>,
+}
+
+impl BulkUpdatePlan {
+    pub fn new(statuses: &[AppStatus]) -> Self {
+        let mut plan = Self::default();
+        for status in statuses {
+            if status.held {
+                plan.held.push(status.name.clone());
+            } else if status.is_updatable() {
+                plan.update.push(status.name.clone());
+            }
+        }
+        plan
+    }
+}
+
+/// 不运行 `scoop status`,根据已安装应用的 `current` 目录和本地 bucket 得出每个应用的状态
+///
+/// bucket 中的版本按 [`versions::compare`] 比已安装的版本新时才算可以更新。
+/// 全局安装的应用与当前用户共用同一组 bucket,`entries` 为当前用户根目录的索引。
+pub fn app_statuses(user_root: &Path, global_root: Option<&Path>, entries: &[IndexEntry]) -> Vec<AppStatus> {
+    let latest: HashMap<(&str, String), &IndexEntry> = entries.iter()
+        .map(|entry| ((entry.bucket.as_str(), entry.name.to_lowercase()), entry))
+        .collect();
+    let roots: Vec<(&Path, Scope)> = std::iter::once((user_root, Scope::User))
+        .chain(global_root.filter(|global_root| *global_root != user_root).map(|root| (root, Scope::Global)))
+        .collect();
+    let is_installed = |app: &str| roots.iter().any(|(root, _)| root.join("apps").join(app).join("current").is_dir());
+
+    let mut statuses: Vec<AppStatus> = roots.iter()
+        .flat_map(|&(root, scope)| installed_app_dirs(root).into_iter().map(move |name| (root, scope, name)))
+        .map(|(root, scope, name)| app_status(user_root, root, scope, name, &latest, &is_installed))
+        .collect();
+    statuses.sort_by(|a, b| a.name.cmp(&b.name).then((a.scope as u8).cmp(&(b.scope as u8))));
+    statuses
+}
+
+/// `apps` 下的应用目录,跳过 scoop 本身
+fn installed_app_dirs(root: &Path) -> Vec<String> {
+    let Ok(apps) = std::fs::read_dir(root.join("apps")) else {
+        return Vec::new();
+    };
+    apps.flatten()
+        .filter(|app| app.path().is_dir())
+        .map(|app| app.file_name().to_string_lossy().to_string())
+        .filter(|name| name != "scoop")
+        .collect()
+}
+
+fn app_status(
+    user_root: &Path,
+    root: &Path,
+    scope: Scope,
+    name: String,
+    latest: &HashMap<(&str, String), &IndexEntry>,
+    is_installed: &dyn Fn(&str) -> bool,
+) -> AppStatus {
+    let current = root.join("apps").join(&name).join("current");
+    let install_info = InstallInfo::read(&current);
+    let manifest = Manifest::read(&current.join("manifest.json")).ok();
+    let mut status = AppStatus {
+        scope,
+        failed: install_info.is_none() || manifest.is_none(),
+        held: install_info.as_ref().is_some_and(|info| info.hold),
+        bucket: install_info.and_then(|info| info.bucket),
+        ..Default::default()
+    };
+
+    if let Some(manifest) = manifest {
+        status.missing_dependencies = manifest.depends
+            .iter()
+            // 依赖可以写成 `bucket/app`
+            .map(|depend| depend.rsplit('/').next().unwrap_or(depend).to_string())
+            .filter(|depend| !is_installed(depend))
+            .collect();
+        status.installed = manifest.version;
+    }
+
+    if let Some(bucket) = &status.bucket {
+        match latest.get(&(bucket.as_str(), name.to_lowercase())) {
+            Some(entry) => {
+                status.outdated = !status.failed && versions::compare(&entry.version, &status.installed).is_gt();
+                status.latest = Some(entry.version.clone());
+            }
+            None => {
+                let deprecated = user_root.join("buckets").join(bucket).join("deprecated").join(format!("{}.json", name));
+                status.deprecated = deprecated.is_file();
+                status.removed = !status.deprecated;
+            }
+        }
+    }
+    status.name = name;
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest_index::ManifestIndex;
+    use crate::tests::fixture_root;
+    use std::path::PathBuf;
+
+    fn write(path: PathBuf, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn install(root: &Path, name: &str, manifest: &str, install_info: &str) {
+        let current = root.join("apps").join(name).join("current");
+        write(current.join("manifest.json"), manifest);
+        write(current.join("install.json"), install_info);
+    }
+
+    #[test]
+    fn finds_outdated_fixture_apps() {
+        let entries = ManifestIndex::in_memory().entries(&fixture_root());
+        let statuses = app_statuses(&fixture_root(), None, &entries);
+        let names: Vec<&str> = statuses.iter().map(|status| status.name.as_str()).collect();
+        assert_eq!(names, ["7zip", "git", "half-removed", "nodejs"]);
+
+        let updatable: Vec<&AppStatus> = statuses.iter().filter(|status| status.is_updatable()).collect();
+        assert_eq!(updatable.len(), 1);
+        assert_eq!(updatable[0].name, "nodejs");
+        assert_eq!((updatable[0].installed.as_str(), updatable[0].latest.as_deref()), ("20.11.0", Some("21.6.1")));
+        assert_eq!(updatable[0].bucket.as_deref(), Some("main"));
+
+        // 没有 current 目录的应用安装失败
+        assert!(statuses[2].failed && !statuses[2].outdated);
+        assert!(!statuses[0].needs_attention());
+
+        // git 已被锁定
+        assert_eq!(BulkUpdatePlan::new(&statuses), BulkUpdatePlan {
+            update: vec!["nodejs".to_string()],
+            held: vec!["git".to_string()],
+        });
+
+        // 同一个目录作为全局根目录时不重复计算
+        assert_eq!(app_statuses(&fixture_root(), Some(&fixture_root()), &entries).len(), 4);
+    }
+
+    #[test]
+    fn reports_held_removed_deprecated_and_missing_dependencies() {
+        let root = std::env::temp_dir().join(format!("scoop-ui-status-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let bucket = root.join("buckets").join("main");
+        write(bucket.join("bucket").join("tool.json"), r#"{"version": "2.0"}"#);
+        write(bucket.join("bucket").join("pinned.json"), r#"{"version": "2.0"}"#);
+        write(bucket.join("bucket").join("lib.json"), r#"{"version": "1.0"}"#);
+        write(bucket.join("deprecated").join("old.json"), r#"{"version": "1.0"}"#);
+        install(&root, "tool", r#"{"version": "1.0", "depends": ["main/lib", "extras/missing"]}"#, r#"{"bucket": "main"}"#);
+        install(&root, "pinned", r#"{"version": "1.0"}"#, r#"{"bucket": "main", "hold": true}"#);
+        install(&root, "lib", r#"{"version": "1.0"}"#, r#"{"bucket": "main"}"#);
+        install(&root, "old", r#"{"version": "1.0"}"#, r#"{"bucket": "main"}"#);
+        install(&root, "gone", r#"{"version": "1.0"}"#, r#"{"bucket": "main"}"#);
+        install(&root, "local", r#"{"version": "1.0"}"#, "{}");
+
+        let entries = ManifestIndex::in_memory().entries(&root);
+        let statuses: HashMap<String, AppStatus> = app_statuses(&root, None, &entries)
+            .into_iter()
+            .map(|status| (status.name.clone(), status))
+            .collect();
+
+        let tool = &statuses["tool"];
+        assert!(tool.is_updatable());
+        assert_eq!(tool.missing_dependencies, ["missing"]);
+        let pinned = &statuses["pinned"];
+        assert!(pinned.outdated && pinned.held && !pinned.is_updatable());
+        assert!(!statuses["lib"].needs_attention());
+        assert!(statuses["old"].deprecated && !statuses["old"].removed);
+        assert!(statuses["gone"].removed && statuses["gone"].latest.is_none());
+        assert!(!statuses["local"].needs_attention() && statuses["local"].bucket.is_none());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,115 @@
+use std::cmp::Ordering;
+
+/// 按 scoop 的规则(`Compare-Version`)比较两个版本号
+///
+/// `+` 视为 `-`;先按 `-` 分段,字母串单独成段,段内有 `.` 或 `_` 时再按它们分段递归比较。
+/// 纯数字的段按数值比较,其余按不区分大小写的字符串比较。
+/// 一方多出的段中含有 `alpha`/`beta`/`rc`/`pre` 时它是预发布版本,更小,否则更大,
+/// 所以 `1.2.3-beta` < `1.2.3` < `1.2.3-1`。两个以 `nightly` 开头的版本总是相等。
+pub fn compare(a: &str, b: &str) -> Ordering {
+    compare_with(&a.replace('+', "-"), &b.replace('+', "-"), '-')
+}
+
+fn compare_with(a: &str, b: &str, delimiter: char) -> Ordering {
+    if a.eq_ignore_ascii_case(b) {
+        return Ordering::Equal;
+    }
+
+    let a_parts = split(a, delimiter);
+    let b_parts = split(b, delimiter);
+    let is_nightly = |parts: &[String]| parts.first().is_some_and(|part| part.eq_ignore_ascii_case("nightly"));
+    if is_nightly(&a_parts) && is_nightly(&b_parts) {
+        return Ordering::Equal;
+    }
+
+    for i in 0..a_parts.len().max(b_parts.len()) {
+        let ordering = match (a_parts.get(i), b_parts.get(i)) {
+            (Some(a), None) => return if is_pre_release(a) { Ordering::Less } else { Ordering::Greater },
+            (None, Some(b)) => return if is_pre_release(b) { Ordering::Greater } else { Ordering::Less },
+            (None, None) => unreachable!(),
+            (Some(a), Some(b)) => {
+                if let Some(delimiter) = ['.', '_'].into_iter().find(|&d| a.contains(d) || b.contains(d)) {
+                    compare_with(a, b, delimiter)
+                } else {
+                    match (a.parse::<u64>(), b.parse::<u64>()) {
+                        (Ok(a), Ok(b)) => a.cmp(&b),
+                        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+                    }
+                }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// 在字母串两侧加上分隔符后分段,丢弃空段,例如 `1.2beta3` 按 `-` 分为 `1.2`、`beta`、`3`
+fn split(version: &str, delimiter: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_letters = false;
+    for c in version.chars() {
+        let is_letter = c.is_ascii_alphabetic();
+        if c == delimiter || (!current.is_empty() && is_letter != in_letters) {
+            parts.push(std::mem::take(&mut current));
+        }
+        if c != delimiter {
+            current.push(c);
+            in_letters = is_letter;
+        }
+    }
+    parts.push(current);
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+fn is_pre_release(part: &str) -> bool {
+    let part = part.to_lowercase();
+    ["alpha", "beta", "rc", "pre"].iter().any(|tag| part.contains(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Ordering::*;
+
+    #[test]
+    fn compares_numeric_parts_by_value() {
+        assert_eq!(compare("1.10", "1.9"), Greater);
+        assert_eq!(compare("21.6.1", "20.11.0"), Greater);
+        assert_eq!(compare("2024.01.05", "2024.01.10"), Less);
+        assert_eq!(compare("2024.01.05", "2024.1.5"), Equal);
+        assert_eq!(compare("1.2", "1.2.0.1"), Less);
+        assert_eq!(compare("2.43.0.windows.1", "2.44.0.windows.1"), Less);
+        assert_eq!(compare("2.43.0.windows.2", "2.43.0.windows.1"), Greater);
+    }
+
+    #[test]
+    fn orders_pre_releases_before_releases() {
+        assert_eq!(compare("1.2.3-beta", "1.2.3"), Less);
+        assert_eq!(compare("1.2.3", "1.2.3-rc1"), Greater);
+        assert_eq!(compare("1.2.3-beta", "1.2.3-rc"), Less);
+        assert_eq!(compare("1.2.3-beta.2", "1.2.3-beta.10"), Less);
+        assert_eq!(compare("1.2.3beta", "1.2.3-beta"), Equal);
+        assert_eq!(compare("3.0.0-preview.1", "3.0.0"), Less);
+        // 多出的段不是预发布标记时是更新的版本
+        assert_eq!(compare("1.2.3-1", "1.2.3"), Greater);
+        assert_eq!(compare("1.2.3+build", "1.2.3"), Greater);
+    }
+
+    #[test]
+    fn treats_nightly_versions_as_equal() {
+        assert_eq!(compare("nightly", "nightly-20240105"), Equal);
+        assert_eq!(compare("Nightly-20240101", "nightly-20240105"), Equal);
+        assert_ne!(compare("nightly", "1.0"), Equal);
+    }
+
+    #[test]
+    fn splits_letter_runs() {
+        assert_eq!(split("1.2beta3", '-'), ["1.2", "beta", "3"]);
+        assert_eq!(split("1.2.3-rc.1", '-'), ["1.2.3", "rc", ".1"]);
+        assert_eq!(split("1.2.3", '.'), ["1", "2", "3"]);
+    }
+}
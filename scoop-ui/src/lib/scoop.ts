@@ -8,6 +8,235 @@ export interface ScoopApp {
     updated: number;        // Unix timestamp in milliseconds
     has_update: boolean;    // Whether an update is available
     install_size: number;   // Size in bytes
+    scope: Scope;           // 当前用户安装还是全局安装 (-g)
+    architecture: string | null;    // 64bit / 32bit / arm64,来自 install.json
+    url: string | null;     // 从 URL 安装时的 manifest 地址
+    held: boolean;          // 已用 scoop hold 锁定版本
+}
+
+export type Scope = 'user' | 'global';
+
+/** 已安装应用的状态,对应 `scoop status` 中的一行 */
+export interface AppStatus {
+    name: string;
+    scope: Scope;
+    bucket: string | null;          // 从 URL 或本地文件安装时为 null
+    installed: string;              // 安装失败时为空
+    latest: string | null;          // bucket 中的版本
+    outdated: boolean;
+    held: boolean;                  // scoop hold,更新时会跳过
+    removed: boolean;               // bucket 中已经没有这个应用
+    deprecated: boolean;
+    missing_dependencies: string[];
+    failed: boolean;
+}
+
+/** `updates-checked` 事件 */
+export interface UpdatesCheckedPayload {
+    updatable_apps: string[];       // 可以更新的应用(不含已锁定的)
+    apps: AppStatus[];              // 可以更新或有问题的应用
+}
+
+/** 后端命令返回的错误,`code` 是稳定的标识 */
+export type ErrorCode =
+    | 'scoop_not_found'
+    | 'no_buckets'
+    | 'manifest_not_found'
+    | 'manifest_invalid'
+    | 'version_unavailable'
+    | 'architecture_unsupported'
+    | 'bucket_exists'
+    | 'bucket_not_found'
+    | 'invalid_argument'
+    | 'invalid_root'
+    | 'elevation_required'
+    | 'command_failed'
+    | 'updates_failed'
+    | 'catalogue_sync_failed'
+    | 'cancelled'
+    | 'job_not_found'
+    | 'job_finished'
+    | 'job_still_running'
+    | 'io';
+
+export interface CommandError {
+    code: ErrorCode;
+    message: string;
+    details: {
+        exit_code?: number;
+        stderr?: string;   // stderr 的最后几行
+        path?: string;
+    };
+}
+
+export function isCommandError(error: unknown, code?: ErrorCode): error is CommandError {
+    if (typeof error !== 'object' || error === null || !('code' in error) || !('message' in error)) {
+        return false;
+    }
+    return code === undefined || (error as CommandError).code === code;
+}
+
+/** 把命令抛出的错误转换为可以展示给用户的文字 */
+export function errorMessage(error: unknown): string {
+    if (!isCommandError(error)) {
+        return String(error);
+    }
+    switch (error.code) {
+        case 'scoop_not_found':
+            return `未找到 Scoop,请确认已经安装 Scoop (${error.message})`;
+        case 'no_buckets':
+            return '还没有添加任何 bucket';
+        case 'bucket_exists':
+            return `${error.message} (bucket 已存在)`;
+        case 'elevation_required':
+            return '全局应用需要以管理员身份运行 Scoop UI';
+        case 'cancelled':
+            return '操作已取消';
+        case 'version_unavailable':
+            return `无法安装指定版本:manifest 中没有 autoupdate (${error.message})`;
+        case 'architecture_unsupported':
+            return `应用不支持所选架构 (${error.message})`;
+        default:
+            return error.message;
+    }
+}
+
+export type JobKind = 'install' | 'uninstall' | 'update' | 'update_all' | 'update_scoop' | 'add_bucket' | 'remove_bucket' | 'check_updates' | 'hold' | 'unhold';
+export type JobStatus = 'queued' | 'running' | 'completed' | 'failed' | 'cancelled';
+
+export interface JobInfo {
+    id: number;
+    kind: JobKind;
+    targets: string[];
+    scope: Scope;
+    status: JobStatus;
+    created_at: number;          // Unix timestamp in milliseconds
+    started_at: number | null;
+    finished_at: number | null;
+    message: string | null;
+    error: CommandError | null;  // 失败时的错误,与命令直接返回的错误格式相同
+    leftover_dirs: string[];     // 取消后留下的未装完的目录
+    app_results: AppResult[];    // 批量更新时每个应用的结果
+}
+
+export type AppOutcome = 'updated' | 'skipped_held' | 'failed';
+
+export interface AppResult {
+    app: string;
+    outcome: AppOutcome;
+    message?: string;
+}
+
+/** 搜索索引保存失败时的事件,负载为 CommandError。索引照常可用,只是下次启动要重新建立 */
+export const INDEX_ERROR_EVENT = 'index-error';
+
+/** 所有任务共用的进度事件名 */
+export const OPERATION_EVENT = 'operation-event';
+
+/** `operation-event` 的负载,字段含义见 src-tauri/src/events.rs */
+export interface OperationEvent {
+    schema_version: number;
+    job_id: number;
+    operation: JobKind;
+    targets: string[];
+    status: JobStatus;
+    phase: { kind: string; [key: string]: unknown } | null;
+    progress: number | null;     // 0.0-1.0
+    bytes_total: number | null;  // 下载阶段要下载的文件大小
+    line: string | null;         // scoop 输出的原始行
+    message: string | null;      // 终态事件的结果或错误信息
+    error: CommandError | null;  // 失败时的错误,命令失败时 details 中有退出码
+    leftover_dirs: string[];
+    app_results: AppResult[];
+    created_at: number;          // Unix timestamp in milliseconds
+    started_at: number | null;
+    timestamp: number;
+}
+
+const OPERATION_VERBS: Record<JobKind, string> = {
+    install: '安装',
+    uninstall: '卸载',
+    update: '更新',
+    update_all: '批量更新',
+    update_scoop: '更新',
+    add_bucket: '添加 bucket',
+    remove_bucket: '删除 bucket',
+    check_updates: '检查更新',
+    hold: '锁定',
+    unhold: '解除锁定',
+};
+
+/** 批量更新中单个应用的结果 */
+export function describeAppResult(result: AppResult): string {
+    switch (result.outcome) {
+        case 'updated':
+            return `${result.app}: 已更新`;
+        case 'skipped_held':
+            return `${result.app}: 已锁定,跳过`;
+        case 'failed':
+            return `${result.app}: 更新失败${result.message ? ` - ${result.message}` : ''}`;
+    }
+}
+
+/** 只把 track 过的任务的事件交给 handler */
+export interface JobEventFilter {
+    handle(event: OperationEvent): void;
+    track(jobId: number): void;
+}
+
+// 提交命令返回 job id 之前任务可能已经开始发送事件,最多暂存这么多条还不知道归属的事件
+const MAX_UNTRACKED_EVENTS = 200;
+
+/**
+ * 按 job id 过滤 `operation-event`,页面只处理自己提交的任务,并发的任务不会互相覆盖进度。
+ * 还没有 track 的任务的事件先暂存,track 时按顺序补发;任务结束后不再跟踪
+ */
+export function jobEventFilter(handler: (event: OperationEvent) => void): JobEventFilter {
+    const tracked = new Set<number>();
+    let untracked: OperationEvent[] = [];
+    const deliver = (event: OperationEvent) => {
+        if (event.status !== 'queued' && event.status !== 'running') {
+            tracked.delete(event.job_id);
+        }
+        handler(event);
+    };
+    return {
+        handle(event) {
+            if (tracked.has(event.job_id)) {
+                deliver(event);
+            } else {
+                untracked = [...untracked, event].slice(-MAX_UNTRACKED_EVENTS);
+            }
+        },
+        track(jobId) {
+            tracked.add(jobId);
+            const early = untracked.filter(event => event.job_id === jobId);
+            untracked = untracked.filter(event => event.job_id !== jobId);
+            early.forEach(deliver);
+        },
+    };
+}
+
+/** 把事件转换成进度窗口里显示的一行文字 */
+export function describeOperationEvent(event: OperationEvent): string {
+    const verb = OPERATION_VERBS[event.operation];
+    const target = event.operation === 'update_all' ? '所有应用'
+        : event.operation === 'update_scoop' ? 'Scoop 和 Buckets'
+        : event.targets.join(', ');
+    switch (event.status) {
+        case 'queued':
+            return `等待其他操作完成后${verb} ${target}...`;
+        case 'running':
+            return event.line ?? `开始${verb} ${target}...`;
+        case 'completed':
+            return event.message ?? `${target} ${verb}完成`;
+        case 'failed':
+            return event.error ? errorMessage(event.error) : event.message ?? `${verb} ${target} 失败`;
+        case 'cancelled':
+            return event.leftover_dirs.length > 0
+                ? `已取消${verb} ${target},以下目录未装完,需要手动删除:\n${event.leftover_dirs.join('\n')}`
+                : `已取消${verb} ${target}`;
+    }
 }
 
 export interface SearchResult {
@@ -15,6 +244,46 @@ export interface SearchResult {
     version: string;
     bucket: string;
     description: string;
+    homepage: string;
+    bins: string[];
+    score: number;              // 相关度,只有本地搜索会填写
+    highlights: Highlight[];
+    installed: boolean;
+    has_update: boolean;
+    bucket_added: boolean;      // 为 false 时应用来自还没有添加的推荐 bucket
+    bucket_url?: string;        // 添加该 bucket 的地址,只在 bucket_added 为 false 时有
+}
+
+export type MatchField = 'name' | 'description' | 'homepage' | 'bin';
+
+/** 命中的一段,start/end 为字符串下标 */
+export interface Highlight {
+    field: MatchField;
+    bin?: number;               // field 为 'bin' 时命中的是第几个 bin
+    start: number;
+    end: number;
+}
+
+export interface TextSegment {
+    text: string;
+    matched: boolean;
+}
+
+/** 按高亮把字段文字切分为若干段,用于渲染 */
+export function highlightSegments(text: string, highlights: Highlight[] = [], field: MatchField, bin?: number): TextSegment[] {
+    const ranges = highlights
+        .filter(h => h.field === field && h.bin === bin)
+        .sort((a, b) => a.start - b.start);
+    const segments: TextSegment[] = [];
+    let pos = 0;
+    for (const { start, end } of ranges) {
+        if (start < pos || end > text.length) continue;
+        if (start > pos) segments.push({ text: text.slice(pos, start), matched: false });
+        segments.push({ text: text.slice(start, end), matched: true });
+        pos = end;
+    }
+    if (pos < text.length) segments.push({ text: text.slice(pos), matched: false });
+    return segments;
 }
 
 export async function getInstalledApps(): Promise<ScoopApp[]> {
@@ -27,19 +296,51 @@ export async function getInstalledApps(): Promise<ScoopApp[]> {
     }
 }
 
-export async function searchApps(query: string): Promise<SearchResult[]> {
+// 搜索请求 id,后端对每种搜索只返回最新一次的结果,所以每种搜索各用一个计数器。
+// 以当前时间为起点,页面刷新后新的 id 仍然比刷新前的大
+function searchIdCounter(): () => number {
+    let lastId = 0;
+    return () => {
+        lastId = Math.max(Date.now(), lastId + 1);
+        return lastId;
+    };
+}
+
+const nextSearchAppsId = searchIdCounter();
+const nextLocalSearchId = searchIdCounter();
+
+/**
+ * 搜索所有 bucket,includeNotAdded 为 true(默认)时也搜索还没有添加的推荐 bucket。
+ * 被更新的搜索取代时返回 null,调用方应忽略
+ */
+export async function searchApps(query: string, includeNotAdded?: boolean): Promise<SearchResult[] | null> {
     try {
-        const results = await invoke<SearchResult[]>('search_apps', { query });
+        const results = await invoke<SearchResult[]>('search_apps', { query, includeNotAdded, requestId: nextSearchAppsId() });
         return results;
     } catch (error) {
+        if (isCommandError(error, 'cancelled')) return null;
         console.error('Failed to search apps:', error);
         return [];
     }
 }
 
-export async function updateApp(appName: string): Promise<string> {
+/**
+ * 下载还没有添加的推荐 bucket,之后 searchApps 也能搜到其中的应用,返回其中的应用数。
+ * 需要联网和 git,只在用户主动请求时调用
+ */
+export async function syncCatalogue(): Promise<number> {
     try {
-        const result = await invoke<string>('update_app', { appName });
+        const count = await invoke<number>('sync_catalogue');
+        return count;
+    } catch (error) {
+        console.error('Failed to sync catalogue:', error);
+        throw error;
+    }
+}
+
+export async function updateApp(appName: string, scope: Scope = 'user'): Promise<JobInfo> {
+    try {
+        const result = await invoke<JobInfo>('update_app', { appName, scope });
         return result;
     } catch (error) {
         console.error('Failed to update app:', error);
@@ -47,9 +348,9 @@ export async function updateApp(appName: string): Promise<string> {
     }
 }
 
-export async function updateAllApps(): Promise<string> {
+export async function updateAllApps(): Promise<JobInfo> {
     try {
-        const result = await invoke<string>('update_all_apps');
+        const result = await invoke<JobInfo>('update_all_apps');
         return result;
     } catch (error) {
         console.error('Failed to update all apps:', error);
@@ -57,9 +358,9 @@ export async function updateAllApps(): Promise<string> {
     }
 }
 
-export async function updateScoop(): Promise<string> {
+export async function updateScoop(): Promise<JobInfo> {
     try {
-        const result = await invoke<string>('update_scoop');
+        const result = await invoke<JobInfo>('update_scoop');
         return result;
     } catch (error) {
         console.error('Failed to update scoop:', error);
@@ -77,16 +378,64 @@ export async function getAppSizes(appNames: string[]): Promise<Record<string, nu
     }
 }
 
-export async function searchLocalApps(query: string): Promise<SearchResult[]> {
+/** 搜索的过滤条件,不填的条件不过滤 */
+export interface SearchFacets {
+    buckets?: string[];
+    licenses?: string[];
+    architecture?: '64bit' | '32bit' | 'arm64';
+    installed?: boolean;
+    has_update?: boolean;
+}
+
+export interface SearchRequest {
+    query: string;              // 为空时按名字列出所有符合条件的应用
+    facets?: SearchFacets;
+    offset?: number;
+    limit?: number;             // 默认 50,最多 200
+    max_typos?: number;         // 为 0 时关闭模糊匹配
+}
+
+export interface LocalSearchResponse {
+    results: SearchResult[];
+    total: number;              // 所有页的结果总数
+    offset: number;
+    suggestions: string[];      // 没有精确命中时的"你是不是要找"
+}
+
+/** 搜索本地 bucket,允许拼写错误,支持按条件过滤和分页。被更新的搜索取代时返回 null */
+export async function searchLocalApps(request: SearchRequest): Promise<LocalSearchResponse | null> {
     try {
-        const results = await invoke<SearchResult[]>('search_local_packets', { query });
-        return results;
+        const response = await invoke<LocalSearchResponse>('search_local_packets', { request, requestId: nextLocalSearchId() });
+        return response;
     } catch (error) {
+        if (isCommandError(error, 'cancelled')) return null;
         console.error('Failed to search local apps:', error);
-        return [];
+        return { results: [], total: 0, offset: request.offset ?? 0, suggestions: [] };
     }
 }
 
+export type BinaryKind = 'shim' | 'shortcut';
+
+export interface Binary {
+    kind: BinaryKind;
+    name: string;
+    target: string;
+}
+
+export interface BinaryProvider {
+    name: string;
+    bucket: string;
+    version: string;
+    description: string;
+    installed: boolean;
+    matches: Binary[];
+}
+
+// 哪些应用提供某个可执行文件(如 rg.exe、code),已安装的排在前面
+export async function findByBinary(name: string): Promise<BinaryProvider[]> {
+    return await invoke<BinaryProvider[]>('find_by_binary', { name });
+}
+
 export interface ScoopBucket {
     name: string;
     source: string;
@@ -123,9 +472,21 @@ export async function removeBucket(name: string): Promise<string> {
     }
 }
 
-export async function installApp(appName: string): Promise<string> {
+export type Architecture = '64bit' | '32bit' | 'arm64';
+
+/** `scoop install` 的选项,省略的字段取默认值 */
+export interface InstallOptions {
+    arch?: Architecture;
+    global?: boolean;
+    no_cache?: boolean;
+    skip_hash_check?: boolean;
+    independent?: boolean;   // 不自动安装依赖
+}
+
+/** 安装应用,指定 version 时安装 `app@version`,由 autoupdate 生成的 manifest 之后不再随 bucket 更新 */
+export async function installApp(appName: string, scope: Scope = 'user', version?: string, options?: InstallOptions): Promise<JobInfo> {
     try {
-        const result = await invoke<string>('install_app', { appName });
+        const result = await invoke<JobInfo>('install_app', { appName, scope, version, options });
         return result;
     } catch (error) {
         console.error('Failed to install app:', error);
@@ -133,9 +494,9 @@ export async function installApp(appName: string): Promise<string> {
     }
 }
 
-export async function uninstallApp(appName: string): Promise<string> {
+export async function uninstallApp(appName: string, scope: Scope = 'user'): Promise<JobInfo> {
     try {
-        const result = await invoke<string>('uninstall_app', { appName });
+        const result = await invoke<JobInfo>('uninstall_app', { appName, scope });
         return result;
     } catch (error) {
         console.error('Failed to uninstall app:', error);
@@ -143,6 +504,26 @@ export async function uninstallApp(appName: string): Promise<string> {
     }
 }
 
+export async function holdApp(appName: string, scope: Scope = 'user'): Promise<string> {
+    try {
+        const result = await invoke<string>('hold_app', { appName, scope });
+        return result;
+    } catch (error) {
+        console.error('Failed to hold app:', error);
+        throw error;
+    }
+}
+
+export async function unholdApp(appName: string, scope: Scope = 'user'): Promise<string> {
+    try {
+        const result = await invoke<string>('unhold_app', { appName, scope });
+        return result;
+    } catch (error) {
+        console.error('Failed to unhold app:', error);
+        throw error;
+    }
+}
+
 export async function checkDependencies(appName: string, bucket?: string): Promise<string[]> {
     try {
         const result = await invoke<string[]>('check_dependencies', { appName, bucket });
@@ -172,3 +553,126 @@ export async function checkUpdatesAsync(): Promise<string> {
         throw error;
     }
 }
+
+export async function listJobs(): Promise<JobInfo[]> {
+    try {
+        const jobs = await invoke<JobInfo[]>('list_jobs');
+        return jobs;
+    } catch (error) {
+        console.error('Failed to list jobs:', error);
+        return [];
+    }
+}
+
+export async function getJob(jobId: number): Promise<JobInfo | null> {
+    try {
+        const job = await invoke<JobInfo>('get_job', { jobId });
+        return job;
+    } catch (error) {
+        console.error('Failed to get job:', error);
+        return null;
+    }
+}
+
+export async function cancelOperation(jobId: number): Promise<JobInfo> {
+    try {
+        const job = await invoke<JobInfo>('cancel_operation', { jobId });
+        return job;
+    } catch (error) {
+        console.error('Failed to cancel operation:', error);
+        throw error;
+    }
+}
+
+export interface HistoryEntry {
+    job_id: number;
+    operation: JobKind;
+    targets: string[];
+    scope: Scope;
+    status: JobStatus;
+    commands: string[];          // 实际执行的命令行
+    exit_code: number | null;
+    created_at: number;          // Unix timestamp in milliseconds
+    started_at: number | null;
+    finished_at: number;
+    message: string | null;
+    versions: Record<string, string>;  // 操作完成后各应用的版本
+    leftover_dirs: string[];
+    app_results: AppResult[];
+}
+
+export interface HistoryFilter {
+    app?: string;
+    since?: number;              // Unix timestamp in milliseconds
+    until?: number;
+    limit?: number;
+}
+
+export async function getOperationHistory(filter: HistoryFilter = {}): Promise<HistoryEntry[]> {
+    try {
+        const entries = await invoke<HistoryEntry[]>('get_operation_history', { ...filter });
+        return entries;
+    } catch (error) {
+        console.error('Failed to get operation history:', error);
+        return [];
+    }
+}
+
+export async function getOperationLog(jobId: number): Promise<string> {
+    try {
+        const log = await invoke<string>('get_operation_log', { jobId });
+        return log;
+    } catch (error) {
+        console.error('Failed to get operation log:', error);
+        throw error;
+    }
+}
+
+export type RootSource = 'override' | 'env' | 'config' | 'default';
+
+export interface ScoopRoot {
+    path: string;
+    source: RootSource;          // 根目录来自界面设置、SCOOP 环境变量、scoop 配置文件还是默认位置
+}
+
+export async function getScoopRoot(): Promise<ScoopRoot> {
+    try {
+        const root = await invoke<ScoopRoot>('get_scoop_root');
+        return root;
+    } catch (error) {
+        console.error('Failed to get scoop root:', error);
+        throw error;
+    }
+}
+
+/** 传 null 清除界面设置,恢复 scoop 自身的规则 */
+export async function setScoopRoot(path: string | null): Promise<ScoopRoot> {
+    try {
+        const root = await invoke<ScoopRoot>('set_scoop_root', { path });
+        return root;
+    } catch (error) {
+        console.error('Failed to set scoop root:', error);
+        throw error;
+    }
+}
+
+export async function getGlobalRoot(): Promise<ScoopRoot> {
+    try {
+        const root = await invoke<ScoopRoot>('get_global_root');
+        return root;
+    } catch (error) {
+        console.error('Failed to get global root:', error);
+        throw error;
+    }
+}
+
+/** 全局应用的安装、更新和卸载需要管理员权限 */
+export async function isElevated(): Promise<boolean> {
+    try {
+        const elevated = await invoke<boolean>('is_elevated_process');
+        return elevated;
+    } catch (error) {
+        console.error('Failed to check elevation:', error);
+        return false;
+    }
+}